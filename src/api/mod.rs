@@ -0,0 +1,136 @@
+mod routes;
+
+use crate::commands::service::PlayerError;
+use crate::state::Data;
+use axum::http::header::{AUTHORIZATION, UPGRADE};
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router, Server};
+use poise::serenity_prelude as serenity;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use url::form_urlencoded;
+
+// shorter tokens are refused when the bot starts
+pub const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Clone)]
+struct ApiState {
+    data: Arc<Data>,
+    token: Arc<str>,
+}
+
+impl ApiState {
+    // commands of the API need the bot's context, it is there once the bot connected
+    fn discord(&self) -> Result<&serenity::Context, ApiError> {
+        self.data.discord.get().ok_or(ApiError::NotReady)
+    }
+}
+
+enum ApiError {
+    Player(PlayerError),
+    NotReady,
+    BadRequest(&'static str),
+    Unauthorized,
+}
+
+impl From<PlayerError> for ApiError {
+    fn from(err: PlayerError) -> Self {
+        ApiError::Player(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::Player(err) => {
+                let status = match err {
+                    PlayerError::NotConnected | PlayerError::NothingPlaying => StatusCode::CONFLICT,
+                    PlayerError::NoSuchTrack | PlayerError::NoSongsFound => StatusCode::NOT_FOUND,
+                    PlayerError::Live | PlayerError::BeyondEnd => StatusCode::BAD_REQUEST,
+                };
+
+                (status, err.to_string())
+            }
+            ApiError::NotReady => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The bot isn't connected yet".to_string(),
+            ),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message.to_string()),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
+        };
+
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+// http api for dashboards on the address, every request needs the token as
+// "Authorization: Bearer <token>", or as "?token=<token>" for websockets of browsers
+pub fn spawn_server(addr: SocketAddr, token: String, data: Arc<Data>) {
+    let state = ApiState {
+        data,
+        token: token.into(),
+    };
+
+    let app = Router::new()
+        .route("/guilds/:guild", get(routes::queue))
+        .route("/guilds/:guild/queue", post(routes::enqueue))
+        .route("/guilds/:guild/queue/move", post(routes::move_track))
+        .route("/guilds/:guild/skip", post(routes::skip))
+        .route("/guilds/:guild/seek", post(routes::seek))
+        .route("/guilds/:guild/pause", post(routes::pause))
+        .route("/guilds/:guild/volume", post(routes::volume))
+        .route("/events", get(routes::events))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state);
+
+    let server = Server::try_bind(&addr)
+        .expect("error binding API server")
+        .serve(app.into_make_service());
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            tracing::error!(error = %err, "API server stopped");
+        }
+    });
+}
+
+async fn authorize<B>(
+    axum::extract::State(state): axum::extract::State<ApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // browsers can't set headers on websockets, other requests have to use the header
+    let query = request
+        .headers()
+        .contains_key(UPGRADE)
+        .then(|| request.uri().query())
+        .flatten()
+        .and_then(|query| {
+            form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "token")
+                .map(|(_, token)| token.into_owned())
+        });
+
+    match header.map(str::to_string).or(query) {
+        Some(token) if same_token(&token, &state.token) => next.run(request).await,
+        _ => ApiError::Unauthorized.into_response(),
+    }
+}
+
+// takes as long for every token of the same length, so it can't be guessed by timing
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
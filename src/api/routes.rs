@@ -0,0 +1,237 @@
+use super::{ApiError, ApiState};
+use crate::commands::service::{self, QueueState, QueuedTrack};
+use crate::player::PlayerEvent;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serenity::{ChannelId, ChannelType, GuildId, User};
+use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Deserialize)]
+pub(super) struct EnqueueRequest {
+    query: String,
+    // where the now playing message is sent, the guild's last one by default
+    channel_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct MoveRequest {
+    from: usize,
+    to: usize,
+}
+
+// also the answer, with the position it got to
+#[derive(Serialize, Deserialize)]
+pub(super) struct SeekRequest {
+    // in seconds
+    position: f64,
+}
+
+#[derive(Deserialize)]
+pub(super) struct PauseRequest {
+    paused: bool,
+}
+
+// also the answer, with the volume after clamping it
+#[derive(Serialize, Deserialize)]
+pub(super) struct VolumeRequest {
+    volume: f32,
+}
+
+#[derive(Deserialize)]
+pub(super) struct EventFilter {
+    // only events of the guild, all of them without it
+    guild: Option<String>,
+}
+
+pub(super) async fn queue(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+) -> ApiResult<Json<QueueState>> {
+    let guild = guild_id(&guild)?;
+    let queue = service::queue_state(state.discord()?, &state.data, guild).await?;
+
+    Ok(Json(queue))
+}
+
+pub(super) async fn enqueue(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+    Json(request): Json<EnqueueRequest>,
+) -> ApiResult<(StatusCode, Json<Vec<QueuedTrack>>)> {
+    let guild = guild_id(&guild)?;
+    let ctx = state.discord()?;
+
+    let last_channel = state
+        .data
+        .guild(guild)
+        .now_playing
+        .lock()
+        .unwrap()
+        .map(|(channel, _)| channel);
+    let channel = match request.channel_id.as_deref() {
+        Some(id) => parse_id(id)
+            .map(ChannelId::new)
+            .filter(|channel| is_message_channel(ctx, guild, *channel))
+            .ok_or(ApiError::BadRequest("Invalid channel id"))?,
+        None => last_channel.ok_or(ApiError::BadRequest(
+            "channel_id is needed until something was played in the guild",
+        ))?,
+    };
+
+    // the bot is shown as who added the songs
+    let author = User::from(ctx.cache.current_user().clone());
+
+    let queued =
+        service::enqueue_query(ctx, &state.data, guild, channel, author, &request.query).await?;
+
+    Ok((StatusCode::CREATED, Json(vec![QueuedTrack::from(&queued)])))
+}
+
+pub(super) async fn move_track(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+    Json(request): Json<MoveRequest>,
+) -> ApiResult<StatusCode> {
+    let guild = guild_id(&guild)?;
+    service::move_track(
+        state.discord()?,
+        &state.data,
+        guild,
+        request.from,
+        request.to,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn skip(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+) -> ApiResult<StatusCode> {
+    let guild = guild_id(&guild)?;
+    service::skip(state.discord()?, &state.data, guild).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn seek(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+    Json(request): Json<SeekRequest>,
+) -> ApiResult<Json<SeekRequest>> {
+    let guild = guild_id(&guild)?;
+    let position = Duration::try_from_secs_f64(request.position)
+        .map_err(|_err| ApiError::BadRequest("Invalid position"))?;
+
+    let position = service::seek(state.discord()?, &state.data, guild, position).await?;
+
+    Ok(Json(SeekRequest {
+        position: position.as_secs_f64(),
+    }))
+}
+
+pub(super) async fn pause(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+    Json(request): Json<PauseRequest>,
+) -> ApiResult<StatusCode> {
+    let guild = guild_id(&guild)?;
+    service::set_paused(state.discord()?, &state.data, guild, request.paused).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn volume(
+    State(state): State<ApiState>,
+    Path(guild): Path<String>,
+    Json(request): Json<VolumeRequest>,
+) -> ApiResult<Json<VolumeRequest>> {
+    let guild = guild_id(&guild)?;
+    if !request.volume.is_finite() {
+        return Err(ApiError::BadRequest("Invalid volume"));
+    }
+
+    let volume = service::set_volume(&state.data, guild, request.volume);
+
+    Ok(Json(VolumeRequest { volume }))
+}
+
+// websocket of player events as json messages
+pub(super) async fn events(
+    State(state): State<ApiState>,
+    Query(filter): Query<EventFilter>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let events = state.data.events.subscribe();
+
+    upgrade.on_upgrade(move |socket| stream_events(socket, events, filter.guild))
+}
+
+async fn stream_events(
+    mut socket: WebSocket,
+    mut events: Receiver<PlayerEvent>,
+    guild: Option<String>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // events the socket was too slow for are dropped
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                if guild.as_deref().is_some_and(|guild| guild != event.guild()) {
+                    continue;
+                }
+
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // pings are answered by axum, the rest is ignored
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+fn guild_id(id: &str) -> ApiResult<GuildId> {
+    parse_id(id)
+        .map(GuildId::new)
+        .ok_or(ApiError::BadRequest("Invalid guild id"))
+}
+
+// text channels of the guild, voice channels have their own chat too
+fn is_message_channel(ctx: &serenity::Context, guild: GuildId, channel: ChannelId) -> bool {
+    let Some(guild) = ctx.cache.guild(guild) else {
+        return false;
+    };
+
+    guild.channels.get(&channel).is_some_and(|channel| {
+        matches!(
+            channel.kind,
+            ChannelType::Text | ChannelType::News | ChannelType::Voice | ChannelType::Stage
+        )
+    })
+}
+
+// discord ids are never 0
+fn parse_id(id: &str) -> Option<u64> {
+    id.parse().ok().filter(|id| *id != 0)
+}
@@ -0,0 +1,90 @@
+use serenity::prelude::TypeMapKey;
+use songbird::tracks::{ControlError, TrackHandle};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const NOT_STARTED: u64 = u64::MAX;
+
+// position of the decoded audio in the original track, songbird's own position
+// only counts played time so it drifts away from it when speed is changed
+#[derive(Clone)]
+pub struct PlaybackClock {
+    position: Arc<AtomicU64>,
+    duration: Arc<AtomicU64>,
+    // end of the clip when only part of the track is played
+    clip_end: Arc<AtomicU64>,
+}
+
+impl Default for PlaybackClock {
+    fn default() -> Self {
+        PlaybackClock {
+            position: Arc::new(AtomicU64::new(NOT_STARTED)),
+            duration: Arc::new(AtomicU64::new(NOT_STARTED)),
+            clip_end: Arc::new(AtomicU64::new(NOT_STARTED)),
+        }
+    }
+}
+
+impl PlaybackClock {
+    pub fn set(&self, position: Duration) {
+        store(&self.position, position);
+    }
+
+    pub fn get(&self) -> Option<Duration> {
+        load(&self.position)
+    }
+
+    pub fn set_duration(&self, duration: Duration) {
+        store(&self.duration, duration);
+    }
+
+    // length of the track without encoder delay and padding, known once it's decoded
+    pub fn duration(&self) -> Option<Duration> {
+        load(&self.duration)
+    }
+
+    pub fn set_clip_end(&self, end: Duration) {
+        store(&self.clip_end, end);
+    }
+
+    // where playback stops, the end of the clip or of the track
+    pub fn end(&self) -> Option<Duration> {
+        match (load(&self.clip_end), self.duration()) {
+            (Some(end), Some(duration)) => Some(end.min(duration)),
+            (end, duration) => end.or(duration),
+        }
+    }
+
+    pub fn clip_ended(&self) -> bool {
+        match (self.get(), load(&self.clip_end)) {
+            (Some(position), Some(end)) => position >= end,
+            _ => false,
+        }
+    }
+}
+
+fn store(millis: &AtomicU64, value: Duration) {
+    millis.store(value.as_millis() as u64, Ordering::Release);
+}
+
+fn load(millis: &AtomicU64) -> Option<Duration> {
+    match millis.load(Ordering::Acquire) {
+        NOT_STARTED => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
+
+impl TypeMapKey for PlaybackClock {
+    type Value = PlaybackClock;
+}
+
+// position of the track on its original timeline
+pub async fn track_position(track: &TrackHandle) -> Result<Duration, ControlError> {
+    let clock = track.typemap().read().await.get::<PlaybackClock>().cloned();
+
+    match clock.and_then(|clock| clock.get()) {
+        Some(position) => Ok(position),
+        None => Ok(track.get_info().await?.position),
+    }
+}
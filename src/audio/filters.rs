@@ -0,0 +1,478 @@
+use super::tempo::TimeStretch;
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+// single stereo sample, every effect works on these
+pub type Frame = [f32; 2];
+
+// center frequencies of the equalizer bands in Hz
+pub const EQ_BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+pub const EQ_MAX_GAIN: f32 = 12.0;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+pub const MAX_PITCH: f32 = 12.0;
+pub const MAX_VOLUME: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    BassBoost,
+    Nightcore,
+    Vaporwave,
+    EightD,
+    Karaoke,
+    Tremolo,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 6] = [
+        Filter::BassBoost,
+        Filter::Nightcore,
+        Filter::Vaporwave,
+        Filter::EightD,
+        Filter::Karaoke,
+        Filter::Tremolo,
+    ];
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Filter::BassBoost => "bassboost",
+            Filter::Nightcore => "nightcore",
+            Filter::Vaporwave => "vaporwave",
+            Filter::EightD => "8d",
+            Filter::Karaoke => "karaoke",
+            Filter::Tremolo => "tremolo",
+        };
+
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct FilterSettings {
+    pub enabled: Vec<Filter>,
+    // gain in dB for every band from EQ_BANDS
+    pub equalizer: [f32; EQ_BANDS.len()],
+    // playback speed multiplier, doesn't change the pitch
+    pub speed: f32,
+    // pitch shift in semitones, doesn't change the speed
+    pub pitch: f32,
+    // gain multiplier of the guild, separate from the volume of tracks used for fading
+    pub volume: f32,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        FilterSettings {
+            enabled: Vec::new(),
+            equalizer: Default::default(),
+            speed: 1.0,
+            pitch: 0.0,
+            volume: 1.0,
+        }
+    }
+}
+
+impl FilterSettings {
+    pub fn is_enabled(&self, filter: Filter) -> bool {
+        self.enabled.contains(&filter)
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.enabled.is_empty() && self.equalizer.iter().all(|gain| *gain == 0.0)
+    }
+}
+
+// filter settings shared between the commands and every playing stream of a guild,
+// version is bumped on each change so streams know when to rebuild their chain
+#[derive(Clone, Default)]
+pub struct FilterHandle {
+    settings: Arc<RwLock<FilterSettings>>,
+    version: Arc<AtomicU64>,
+}
+
+impl FilterHandle {
+    pub fn settings(&self) -> FilterSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    // returns whether the filter is enabled after toggling
+    pub fn toggle(&self, filter: Filter) -> bool {
+        self.update(|settings| {
+            if let Some(index) = settings.enabled.iter().position(|f| *f == filter) {
+                settings.enabled.remove(index);
+                return false;
+            }
+
+            // nightcore and vaporwave both change the playback rate
+            settings.enabled.retain(|f| {
+                !matches!(
+                    (filter, f),
+                    (Filter::Nightcore, Filter::Vaporwave) | (Filter::Vaporwave, Filter::Nightcore)
+                )
+            });
+            settings.enabled.push(filter);
+
+            true
+        })
+    }
+
+    pub fn set_band(&self, band: usize, gain: f32) {
+        self.update(|settings| settings.equalizer[band] = gain.clamp(-EQ_MAX_GAIN, EQ_MAX_GAIN));
+    }
+
+    pub fn set_speed(&self, speed: f32) {
+        self.update(|settings| settings.speed = speed.clamp(MIN_SPEED, MAX_SPEED));
+    }
+
+    pub fn set_pitch(&self, semitones: f32) {
+        self.update(|settings| settings.pitch = semitones.clamp(-MAX_PITCH, MAX_PITCH));
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.update(|settings| settings.volume = volume.clamp(0.0, MAX_VOLUME));
+    }
+
+    pub fn reset_equalizer(&self) {
+        self.update(|settings| settings.equalizer = Default::default());
+    }
+
+    // speed, pitch and volume have their own controls so they are kept
+    pub fn clear(&self) {
+        self.update(|settings| {
+            settings.enabled.clear();
+            settings.equalizer = Default::default();
+        });
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut FilterSettings) -> T) -> T {
+        let result = f(&mut self.settings.write().unwrap());
+        self.version.fetch_add(1, Ordering::AcqRel);
+
+        result
+    }
+}
+
+pub(super) trait Effect: Send + Sync {
+    fn process(&mut self, frames: &mut Vec<Frame>);
+}
+
+// chain of effects built from the current settings of a stream
+pub struct FilterChain {
+    effects: Vec<Box<dyn Effect>>,
+}
+
+impl FilterChain {
+    pub fn new(settings: &FilterSettings, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+        let mut effects: Vec<Box<dyn Effect>> = Vec::new();
+
+        let bands = settings
+            .equalizer
+            .iter()
+            .zip(EQ_BANDS)
+            .filter(|(gain, freq)| **gain != 0.0 && *freq < rate / 2.0)
+            .map(|(gain, freq)| Biquad::peaking(rate, freq, 1.4, *gain))
+            .collect::<Vec<_>>();
+        if !bands.is_empty() {
+            effects.push(Box::new(Equalizer { bands }));
+        }
+
+        // pitch is shifted by resampling, stretching compensates the speed change it causes
+        let pitch_ratio = 2f64.powf(settings.pitch as f64 / 12.0);
+        let tempo = settings.speed as f64 / pitch_ratio;
+        if (tempo - 1.0).abs() > f64::EPSILON {
+            effects.push(Box::new(TimeStretch::new(sample_rate, tempo)));
+        }
+        if (pitch_ratio - 1.0).abs() > f64::EPSILON {
+            effects.push(Box::new(Resampler::new(pitch_ratio)));
+        }
+
+        // order is fixed so toggling one filter doesn't change how the others sound
+        for filter in Filter::ALL {
+            if !settings.is_enabled(filter) {
+                continue;
+            }
+
+            let effect: Box<dyn Effect> = match filter {
+                Filter::BassBoost => Box::new(Biquad::low_shelf(rate, 110.0, 0.7, 9.0)),
+                Filter::Nightcore => Box::new(Resampler::new(1.25)),
+                Filter::Vaporwave => Box::new(Resampler::new(0.8)),
+                Filter::EightD => Box::new(AutoPan::new(rate, 0.125)),
+                Filter::Karaoke => Box::new(Karaoke::new(rate)),
+                Filter::Tremolo => Box::new(Tremolo::new(rate, 4.0, 0.5)),
+            };
+            effects.push(effect);
+        }
+
+        if settings.volume != 1.0 {
+            effects.push(Box::new(Gain(settings.volume)));
+        }
+
+        FilterChain { effects }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn process(&mut self, frames: &mut Vec<Frame>) {
+        for effect in &mut self.effects {
+            effect.process(frames);
+        }
+    }
+}
+
+// RBJ audio eq cookbook filter, keeps separate state for both channels
+pub(super) struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    state: [[f32; 4]; 2],
+}
+
+impl Biquad {
+    pub(super) fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Biquad {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            state: [[0.0; 4]; 2],
+        }
+    }
+
+    fn peaking(rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+
+        Biquad::new(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    fn low_shelf(rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+        let sqrt = 2.0 * a.sqrt() * alpha;
+
+        Biquad::new(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + sqrt,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt,
+            ],
+        )
+    }
+
+    fn low_pass(rate: f32, freq: f32, q: f32) -> Self {
+        let w0 = 2.0 * PI * freq / rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+
+        Biquad::new(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub(super) fn tick(&mut self, channel: usize, x: f32) -> f32 {
+        let [x1, x2, y1, y2] = self.state[channel];
+        let y = self.b[0] * x + self.b[1] * x1 + self.b[2] * x2 - self.a[0] * y1 - self.a[1] * y2;
+        self.state[channel] = [x, x1, y, y1];
+
+        y
+    }
+}
+
+impl Effect for Biquad {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for frame in frames.iter_mut() {
+            frame[0] = self.tick(0, frame[0]);
+            frame[1] = self.tick(1, frame[1]);
+        }
+    }
+}
+
+struct Equalizer {
+    bands: Vec<Biquad>,
+}
+
+impl Effect for Equalizer {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for band in &mut self.bands {
+            band.process(frames);
+        }
+    }
+}
+
+// changes speed and pitch together by linear interpolation
+struct Resampler {
+    ratio: f64,
+    pos: f64,
+    prev: Frame,
+    output: Vec<Frame>,
+}
+
+impl Resampler {
+    fn new(ratio: f64) -> Self {
+        Resampler {
+            ratio,
+            pos: 0.0,
+            prev: [0.0; 2],
+            output: Vec::new(),
+        }
+    }
+}
+
+impl Effect for Resampler {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        if frames.is_empty() {
+            return;
+        }
+
+        // position is relative to the last frame of the previous buffer
+        let at = |i: usize| if i == 0 { self.prev } else { frames[i - 1] };
+
+        self.output.clear();
+        while self.pos < frames.len() as f64 {
+            let index = self.pos as usize;
+            let frac = (self.pos - index as f64) as f32;
+            let (a, b) = (at(index), at(index + 1));
+
+            self.output
+                .push([a[0] + (b[0] - a[0]) * frac, a[1] + (b[1] - a[1]) * frac]);
+            self.pos += self.ratio;
+        }
+
+        self.pos -= frames.len() as f64;
+        self.prev = frames[frames.len() - 1];
+        std::mem::swap(frames, &mut self.output);
+    }
+}
+
+// low frequency oscillator shared by the modulation effects
+struct Lfo {
+    phase: f32,
+    step: f32,
+}
+
+impl Lfo {
+    fn new(rate: f32, freq: f32) -> Self {
+        Lfo {
+            phase: 0.0,
+            step: 2.0 * PI * freq / rate,
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        let value = self.phase.sin();
+        self.phase = (self.phase + self.step) % (2.0 * PI);
+
+        value
+    }
+}
+
+// slowly moves the sound around the listener's head
+struct AutoPan {
+    lfo: Lfo,
+}
+
+impl AutoPan {
+    fn new(rate: f32, freq: f32) -> Self {
+        AutoPan {
+            lfo: Lfo::new(rate, freq),
+        }
+    }
+}
+
+impl Effect for AutoPan {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for frame in frames.iter_mut() {
+            let pan = self.lfo.next();
+            let mid = (frame[0] + frame[1]) / 2.0;
+
+            // equal power panning, both channels at full volume in the middle
+            frame[0] = mid * (1.0 - pan).sqrt();
+            frame[1] = mid * (1.0 + pan).sqrt();
+        }
+    }
+}
+
+// removes the center channel where vocals usually are, keeping the bass
+struct Karaoke {
+    low_pass: Biquad,
+}
+
+impl Karaoke {
+    fn new(rate: f32) -> Self {
+        Karaoke {
+            low_pass: Biquad::low_pass(rate, 150.0, 0.7),
+        }
+    }
+}
+
+impl Effect for Karaoke {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for frame in frames.iter_mut() {
+            let mid = (frame[0] + frame[1]) / 2.0;
+            let side = (frame[0] - frame[1]) / 2.0;
+            let bass = self.low_pass.tick(0, mid);
+
+            *frame = [bass + side, bass - side];
+        }
+    }
+}
+
+struct Tremolo {
+    lfo: Lfo,
+    depth: f32,
+}
+
+impl Tremolo {
+    fn new(rate: f32, freq: f32, depth: f32) -> Self {
+        Tremolo {
+            lfo: Lfo::new(rate, freq),
+            depth,
+        }
+    }
+}
+
+impl Effect for Tremolo {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for frame in frames.iter_mut() {
+            let gain = 1.0 - self.depth * (0.5 + 0.5 * self.lfo.next());
+
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+}
+
+struct Gain(f32);
+
+impl Effect for Gain {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        for frame in frames.iter_mut() {
+            frame[0] *= self.0;
+            frame[1] *= self.0;
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use super::filters::{Biquad, Frame};
+use crate::lru::LruMap;
+use songbird::input::Parsed;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex};
+use symphonia::core::meta::{MetadataRevision, StandardTagKey, Value};
+
+// ReplayGain 2.0 reference level
+const REPLAYGAIN_REFERENCE: f32 = -18.0;
+// how much audio is measured before playback when there is no ReplayGain or cached value
+pub const MEASURE_SECS: u64 = 6;
+const LIMITER_THRESHOLD: f32 = 0.891; // -1 dBFS
+const LIMITER_RELEASE_MS: f32 = 100.0;
+// gain is kept in a sane range for silent or broken measurements
+const MAX_GAIN_DB: f32 = 15.0;
+// tracks whose loudness is remembered, the least recently played are forgotten first
+const LOUDNESS_CACHE_CAPACITY: usize = 4096;
+
+// measured integrated loudness of tracks, keyed by their url
+#[derive(Clone)]
+pub struct LoudnessCache(Arc<Mutex<LruMap<String, f32>>>);
+
+impl Default for LoudnessCache {
+    fn default() -> Self {
+        LoudnessCache(Arc::new(Mutex::new(LruMap::new(LOUDNESS_CACHE_CAPACITY))))
+    }
+}
+
+impl LoudnessCache {
+    pub fn get(&self, key: &str) -> Option<f32> {
+        self.0.lock().unwrap().get_mut(key).copied()
+    }
+
+    pub fn insert(&self, key: String, loudness: f32) {
+        self.0.lock().unwrap().insert(key, loudness);
+    }
+}
+
+// everything a stream needs to normalize a track toward the target loudness
+#[derive(Clone)]
+pub struct Normalization {
+    pub target: f32,
+    pub key: String,
+    pub cache: LoudnessCache,
+}
+
+impl Normalization {
+    pub fn gain(&self, loudness: f32) -> f32 {
+        let gain_db = (self.target - loudness).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+
+        10f32.powf(gain_db / 20.0)
+    }
+}
+
+// loudness of the track from its ReplayGain tags, in LUFS
+pub fn replaygain_loudness(parsed: &mut Parsed) -> Option<f32> {
+    let from_probe = parsed
+        .meta
+        .get()
+        .and_then(|meta| meta.current().and_then(track_gain));
+
+    from_probe
+        .or_else(|| parsed.format.metadata().current().and_then(track_gain))
+        .map(|gain| REPLAYGAIN_REFERENCE - gain)
+}
+
+fn track_gain(revision: &MetadataRevision) -> Option<f32> {
+    let tag = revision
+        .tags()
+        .iter()
+        .find(|tag| tag.std_key == Some(StandardTagKey::ReplayGainTrackGain))?;
+
+    match &tag.value {
+        Value::Float(gain) => Some(*gain as f32),
+        // usually written as "-6.54 dB"
+        Value::String(gain) => gain.trim().trim_end_matches("dB").trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// EBU R128 integrated loudness meter
+pub struct LoudnessMeter {
+    // K-weighting, high shelf followed by a high pass
+    shelf: Biquad,
+    high_pass: Biquad,
+    step_len: usize,
+    step_frames: usize,
+    step_sum: f64,
+    // mean square of every 100ms step, gating blocks are made of 4 of them
+    steps: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+
+        // coefficients for any sample rate, as derived in libebur128
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let shelf = Biquad::new(
+            to_f32([
+                vh + vb * k / q + k * k,
+                2.0 * (k * k - vh),
+                vh - vb * k / q + k * k,
+            ]),
+            to_f32([
+                1.0 + k / q + k * k,
+                2.0 * (k * k - 1.0),
+                1.0 - k / q + k * k,
+            ]),
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / rate).tan();
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            to_f32([
+                1.0 + k / q + k * k,
+                2.0 * (k * k - 1.0),
+                1.0 - k / q + k * k,
+            ]),
+        );
+
+        LoudnessMeter {
+            shelf,
+            high_pass,
+            step_len: (sample_rate / 10) as usize,
+            step_frames: 0,
+            step_sum: 0.0,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, frames: &[Frame]) {
+        for frame in frames {
+            for (channel, sample) in frame.iter().enumerate() {
+                let weighted = self
+                    .high_pass
+                    .tick(channel, self.shelf.tick(channel, *sample))
+                    as f64;
+                self.step_sum += weighted * weighted;
+            }
+
+            self.step_frames += 1;
+            if self.step_frames == self.step_len {
+                self.steps.push(self.step_sum / self.step_len as f64);
+                self.step_frames = 0;
+                self.step_sum = 0.0;
+            }
+        }
+    }
+
+    pub fn integrated(&self) -> Option<f32> {
+        let blocks = self
+            .steps
+            .windows(4)
+            .map(|steps| steps.iter().sum::<f64>() / 4.0)
+            .collect::<Vec<_>>();
+
+        // absolute gate at -70 LUFS, then relative gate 10 LU below the result
+        let absolute = gated_mean(&blocks, loudness_to_power(-70.0))?;
+        let relative = gated_mean(&blocks, absolute * 0.1)?;
+
+        Some(power_to_loudness(relative) as f32)
+    }
+}
+
+fn gated_mean(blocks: &[f64], threshold: f64) -> Option<f64> {
+    let gated = blocks
+        .iter()
+        .filter(|power| **power > threshold)
+        .collect::<Vec<_>>();
+
+    if gated.is_empty() {
+        return None;
+    }
+
+    Some(gated.iter().copied().sum::<f64>() / gated.len() as f64)
+}
+
+fn power_to_loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn loudness_to_power(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+fn to_f32(coefficients: [f64; 3]) -> [f32; 3] {
+    coefficients.map(|c| c as f32)
+}
+
+// applies the normalization gain and keeps peaks below -1 dBFS, the ones of the guild's
+// volume too
+pub struct Limiter {
+    gain: f32,
+    reduction: f32,
+    release: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: u32, gain: f32) -> Self {
+        Limiter {
+            gain,
+            reduction: 1.0,
+            release: 1.0 - (-1.0 / (LIMITER_RELEASE_MS / 1000.0 * sample_rate as f32)).exp(),
+        }
+    }
+
+    pub fn process(&mut self, frames: &mut [Frame]) {
+        for frame in frames.iter_mut() {
+            let peak = frame[0].abs().max(frame[1].abs()) * self.gain;
+
+            // instant attack, smooth release
+            let needed = if peak > LIMITER_THRESHOLD {
+                LIMITER_THRESHOLD / peak
+            } else {
+                1.0
+            };
+            self.reduction = if needed < self.reduction {
+                needed
+            } else {
+                self.reduction + (needed - self.reduction) * self.release
+            };
+
+            let gain = self.gain * self.reduction;
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+}
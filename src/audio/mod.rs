@@ -0,0 +1,14 @@
+mod clock;
+mod filters;
+mod loudness;
+mod stream;
+mod tags;
+mod tempo;
+
+pub use clock::{track_position, PlaybackClock};
+pub use filters::{
+    Filter, FilterHandle, EQ_BANDS, EQ_MAX_GAIN, MAX_PITCH, MAX_SPEED, MAX_VOLUME, MIN_SPEED,
+};
+pub use loudness::{LoudnessCache, Normalization};
+pub use stream::DspCompose;
+pub use tags::EmbeddedLyrics;
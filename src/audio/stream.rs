@@ -0,0 +1,464 @@
+use super::clock::PlaybackClock;
+use super::filters::{FilterChain, FilterHandle, Frame};
+use super::loudness::{replaygain_loudness, Limiter, LoudnessMeter, Normalization, MEASURE_SECS};
+use super::tags::EmbeddedLyrics;
+use serenity::async_trait;
+use songbird::input::{
+    codecs::{CODEC_REGISTRY, PROBE},
+    AudioStream, AudioStreamError, AuxMetadata, Compose, Input, LiveInput, Parsed, RawAdapter,
+};
+use std::collections::VecDeque;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom};
+use std::time::Duration;
+use symphonia::core::{
+    audio::{AudioBuffer, Signal},
+    errors::Error as SymphError,
+    formats::{SeekMode, SeekTo},
+    io::MediaSource,
+    units::Time,
+};
+
+// size of the header RawAdapter puts in front of our samples
+const RAW_HEADER_LEN: u64 = 16;
+const FRAME_LEN: u64 = std::mem::size_of::<Frame>() as u64;
+// decoded ahead when the stream is created, so preloaded tracks start instantly
+const PREDECODE: Duration = Duration::from_secs(1);
+
+// compose wrapper that decodes the inner source itself and routes it through the guild's filters
+pub struct DspCompose {
+    inner: Box<dyn Compose>,
+    filters: FilterHandle,
+    clock: PlaybackClock,
+    normalization: Option<Normalization>,
+    lyrics: EmbeddedLyrics,
+}
+
+impl DspCompose {
+    // only lazy inputs can be wrapped, live ones are already handed to songbird's decoder
+    pub fn wrap(
+        input: Input,
+        filters: FilterHandle,
+        clock: PlaybackClock,
+        normalization: Option<Normalization>,
+        lyrics: EmbeddedLyrics,
+    ) -> Input {
+        match input {
+            Input::Lazy(inner) => Input::Lazy(Box::new(DspCompose {
+                inner,
+                filters,
+                clock,
+                normalization,
+                lyrics,
+            })),
+            input => input,
+        }
+    }
+}
+
+#[async_trait]
+impl Compose for DspCompose {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let stream = self.inner.create()?;
+
+        DspStream::new(
+            stream,
+            self.filters.clone(),
+            self.clock.clone(),
+            self.normalization.clone(),
+            self.lyrics.clone(),
+        )
+        .map(DspStream::into_audio_stream)
+    }
+
+    async fn create_async(
+        &mut self,
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let stream = self.inner.create_async().await?;
+        let filters = self.filters.clone();
+        let clock = self.clock.clone();
+        let normalization = self.normalization.clone();
+        let lyrics = self.lyrics.clone();
+
+        // probing reads from the source so it can't run on the async runtime
+        tokio::task::spawn_blocking(move || {
+            DspStream::new(stream, filters, clock, normalization, lyrics)
+        })
+        .await
+        .map_err(|err| AudioStreamError::Fail(err.into()))?
+        .map(DspStream::into_audio_stream)
+    }
+
+    fn should_create_async(&self) -> bool {
+        self.inner.should_create_async()
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        self.inner.aux_metadata().await
+    }
+}
+
+// decoded and filtered audio exposed as a raw f32 stereo stream
+pub struct DspStream {
+    parsed: Parsed,
+    sample_rate: u32,
+    filters: FilterHandle,
+    filters_version: u64,
+    chain: FilterChain,
+    clock: PlaybackClock,
+    normalization: Option<Normalization>,
+    // measures the whole track when its loudness wasn't known beforehand
+    meter: Option<LoudnessMeter>,
+    // last in the chain, so volumes above 1 can't clip either
+    limiter: Limiter,
+    // audio decoded ahead to measure its loudness, with the position of each chunk
+    preroll: VecDeque<(u64, Vec<Frame>)>,
+    buffer: Option<AudioBuffer<f32>>,
+    frames: Vec<Frame>,
+    pending: Vec<u8>,
+    pending_pos: usize,
+    // frames left to drop after an inaccurate seek of the container
+    skip_frames: u64,
+    // encoder delay, and the end of the audio before encoder padding, in frames
+    delay: u64,
+    end: Option<u64>,
+}
+
+impl DspStream {
+    fn new(
+        stream: AudioStream<Box<dyn MediaSource>>,
+        filters: FilterHandle,
+        clock: PlaybackClock,
+        normalization: Option<Normalization>,
+        lyrics: EmbeddedLyrics,
+    ) -> Result<Self, AudioStreamError> {
+        let mut parsed = match LiveInput::Raw(stream).promote(&CODEC_REGISTRY, &PROBE) {
+            Ok(LiveInput::Parsed(parsed)) => parsed,
+            Ok(_) => return Err(AudioStreamError::Unsupported),
+            Err(err) => return Err(AudioStreamError::Fail(err.into())),
+        };
+
+        lyrics.read(&mut parsed);
+
+        let sample_rate = parsed
+            .decoder
+            .codec_params()
+            .sample_rate
+            .ok_or(AudioStreamError::Unsupported)?;
+
+        let filters_version = filters.version();
+        let chain = FilterChain::new(&filters.settings(), sample_rate);
+
+        let params = parsed.decoder.codec_params();
+        let delay = params.delay.unwrap_or_default() as u64;
+        let padding = params.padding.unwrap_or_default() as u64;
+        let n_frames = params.n_frames;
+
+        let mut stream = DspStream {
+            parsed,
+            sample_rate,
+            filters,
+            filters_version,
+            chain,
+            clock,
+            normalization,
+            meter: None,
+            limiter: Limiter::new(sample_rate, 1.0),
+            preroll: VecDeque::new(),
+            buffer: None,
+            frames: Vec::new(),
+            pending: Vec::new(),
+            pending_pos: 0,
+            skip_frames: 0,
+            delay,
+            end: None,
+        };
+
+        // containers like mkv store the length in their own time base
+        if let Some(n_frames) = n_frames {
+            let end = stream.ts_to_frames(n_frames).saturating_sub(padding);
+            stream.end = Some(end);
+            stream
+                .clock
+                .set_duration(stream.frames_to_duration(end.saturating_sub(delay)));
+        }
+        stream
+            .prepare_normalization()
+            .and_then(|_| stream.predecode(PREDECODE))
+            .map_err(|err| AudioStreamError::Fail(err.into()))?;
+
+        Ok(stream)
+    }
+
+    fn into_audio_stream(self) -> AudioStream<Box<dyn MediaSource>> {
+        let sample_rate = self.sample_rate;
+
+        AudioStream {
+            input: Box::new(RawAdapter::new(self, sample_rate, 2)),
+            hint: None,
+        }
+    }
+
+    // finds the track's loudness from its tags, the cache or by measuring its beginning
+    fn prepare_normalization(&mut self) -> IoResult<()> {
+        let Some(normalization) = self.normalization.clone() else {
+            return Ok(());
+        };
+
+        let known = replaygain_loudness(&mut self.parsed)
+            .or_else(|| normalization.cache.get(&normalization.key));
+
+        let loudness = match known {
+            Some(loudness) => loudness,
+            None => {
+                self.meter = Some(LoudnessMeter::new(self.sample_rate));
+
+                self.predecode(Duration::from_secs(MEASURE_SECS))?;
+
+                // silence, nothing to normalize
+                let Some(loudness) = self.meter.as_ref().and_then(LoudnessMeter::integrated) else {
+                    return Ok(());
+                };
+                normalization
+                    .cache
+                    .insert(normalization.key.clone(), loudness);
+
+                loudness
+            }
+        };
+
+        self.limiter = Limiter::new(self.sample_rate, normalization.gain(loudness));
+
+        Ok(())
+    }
+
+    // decodes the beginning ahead so playback can start without waiting for the source
+    fn predecode(&mut self, length: Duration) -> IoResult<()> {
+        let target = (length.as_secs_f64() * self.sample_rate as f64) as usize;
+        let mut decoded = self
+            .preroll
+            .iter()
+            .map(|(_, frames)| frames.len())
+            .sum::<usize>();
+
+        while decoded < target {
+            let Some(position) = self.decode_next()? else {
+                break;
+            };
+            decoded += self.frames.len();
+            self.preroll
+                .push_back((position, std::mem::take(&mut self.frames)));
+        }
+
+        Ok(())
+    }
+
+    // caches loudness of the whole track once it was decoded from the start to the end
+    fn finish_measurement(&mut self) {
+        let (Some(meter), Some(normalization)) = (self.meter.take(), &self.normalization) else {
+            return;
+        };
+
+        if let Some(loudness) = meter.integrated() {
+            normalization
+                .cache
+                .insert(normalization.key.clone(), loudness);
+        }
+    }
+
+    // filters and serializes the next chunk of audio, returns false at the end of the stream
+    fn fill_pending(&mut self) -> IoResult<bool> {
+        loop {
+            let position = match self.preroll.pop_front() {
+                Some((position, frames)) => {
+                    self.frames = frames;
+                    position
+                }
+                None => match self.decode_next()? {
+                    Some(position) => position,
+                    None => {
+                        self.finish_measurement();
+                        return Ok(false);
+                    }
+                },
+            };
+
+            self.clock.set(self.frames_to_duration(position));
+
+            self.apply_filters();
+
+            self.limiter.process(&mut self.frames);
+
+            if self.frames.is_empty() {
+                continue;
+            }
+
+            self.pending.clear();
+            self.pending_pos = 0;
+            for frame in &self.frames {
+                self.pending.extend_from_slice(&frame[0].to_le_bytes());
+                self.pending.extend_from_slice(&frame[1].to_le_bytes());
+            }
+
+            return Ok(true);
+        }
+    }
+
+    // decodes the next packet into frames, returns their position or None at the end of the stream
+    fn decode_next(&mut self) -> IoResult<Option<u64>> {
+        loop {
+            let packet = match self.parsed.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(SymphError::ResetRequired) => {
+                    self.parsed.decoder.reset();
+                    continue;
+                }
+                Err(err) => return Err(IoError::other(err)),
+            };
+
+            if packet.track_id() != self.parsed.track_id {
+                continue;
+            }
+            let position = self.ts_to_frames(packet.ts());
+
+            let decoded = match self.parsed.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // skip malformed packets like songbird does
+                Err(SymphError::DecodeError(_)) => continue,
+                Err(err) => return Err(IoError::other(err)),
+            };
+
+            let buffer = match &mut self.buffer {
+                Some(buffer) if buffer.capacity() >= decoded.capacity() => buffer,
+                buffer => buffer.insert(decoded.make_equivalent()),
+            };
+            decoded.convert(buffer);
+
+            let skip = self.skip_frames.min(buffer.frames() as u64) as usize;
+            self.skip_frames -= skip as u64;
+
+            // encoder delay at the start and padding at the end aren't part of the track
+            let start = position + skip as u64;
+            let len = (buffer.frames() - skip) as u64;
+            let trim_start = self.delay.saturating_sub(start).min(len) as usize;
+            let trim_end =
+                self.end
+                    .map_or(len, |end| end.saturating_sub(start).min(len)) as usize;
+
+            let left = buffer.chan(0);
+            let right = buffer.chan(buffer.spec().channels.count().min(2) - 1);
+            self.frames.clear();
+            self.frames.extend(
+                left.iter()
+                    .zip(right)
+                    .skip(skip)
+                    .take(trim_end)
+                    .skip(trim_start)
+                    .map(|(l, r)| [*l, *r]),
+            );
+
+            if let Some(meter) = &mut self.meter {
+                meter.add(&self.frames);
+            }
+
+            return Ok(Some((start + trim_start as u64).saturating_sub(self.delay)));
+        }
+    }
+
+    fn ts_to_frames(&self, ts: u64) -> u64 {
+        let Some(time_base) = self.parsed.decoder.codec_params().time_base else {
+            return ts;
+        };
+
+        let time = time_base.calc_time(ts);
+        time.seconds * self.sample_rate as u64 + (time.frac * self.sample_rate as f64) as u64
+    }
+
+    fn frames_to_duration(&self, frames: u64) -> Duration {
+        Duration::from_secs_f64(frames as f64 / self.sample_rate as f64)
+    }
+
+    fn apply_filters(&mut self) {
+        // filters were changed mid track
+        let version = self.filters.version();
+        if version != self.filters_version {
+            self.filters_version = version;
+            self.chain = FilterChain::new(&self.filters.settings(), self.sample_rate);
+        }
+
+        if !self.chain.is_empty() {
+            self.chain.process(&mut self.frames);
+        }
+    }
+}
+
+impl Read for DspStream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.pending_pos >= self.pending.len() && !self.fill_pending()? {
+            return Ok(0);
+        }
+
+        let pending = &self.pending[self.pending_pos..];
+        let len = pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&pending[..len]);
+        self.pending_pos += len;
+
+        Ok(len)
+    }
+}
+
+impl Seek for DspStream {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        // RawReader only seeks to absolute positions, which include the raw header
+        let SeekFrom::Start(pos) = pos else {
+            return Err(ErrorKind::Unsupported.into());
+        };
+
+        // songbird asks for positions on the original timeline, even when the
+        // speed is changed and the stream is shorter or longer than the track
+        let frame = pos.saturating_sub(RAW_HEADER_LEN) / FRAME_LEN;
+        let target = self.frames_to_duration(frame);
+        let container_time = self.frames_to_duration(frame + self.delay);
+        let time = Time::new(
+            container_time.as_secs(),
+            container_time.subsec_nanos() as f64 / 1e9,
+        );
+
+        let seeked = self
+            .parsed
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time,
+                    track_id: Some(self.parsed.track_id),
+                },
+            )
+            .map_err(IoError::other)?;
+
+        self.parsed.decoder.reset();
+        self.chain = FilterChain::new(&self.filters.settings(), self.sample_rate);
+        self.filters_version = self.filters.version();
+        self.pending.clear();
+        self.pending_pos = 0;
+        // the rest of the track wouldn't be measured from the start anymore
+        self.preroll.clear();
+        self.meter = None;
+        self.skip_frames = self.ts_to_frames(seeked.required_ts.saturating_sub(seeked.actual_ts));
+        self.clock.set(target);
+
+        Ok(frame * FRAME_LEN)
+    }
+}
+
+impl MediaSource for DspStream {
+    fn is_seekable(&self) -> bool {
+        self.parsed.supports_backseek
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
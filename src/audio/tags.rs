@@ -0,0 +1,45 @@
+use serenity::prelude::TypeMapKey;
+use songbird::input::Parsed;
+use std::sync::{Arc, OnceLock};
+use symphonia::core::meta::{MetadataRevision, StandardTagKey};
+
+// lyrics found in the tags of a track, known once its stream is created
+#[derive(Clone, Default)]
+pub struct EmbeddedLyrics(Arc<OnceLock<String>>);
+
+impl EmbeddedLyrics {
+    pub fn get(&self) -> Option<&str> {
+        self.0.get().map(String::as_str)
+    }
+
+    pub(super) fn read(&self, parsed: &mut Parsed) {
+        let from_probe = parsed
+            .meta
+            .get()
+            .and_then(|meta| meta.current().and_then(lyrics));
+
+        if let Some(lyrics) =
+            from_probe.or_else(|| parsed.format.metadata().current().and_then(lyrics))
+        {
+            let _ = self.0.set(lyrics);
+        }
+    }
+}
+
+impl TypeMapKey for EmbeddedLyrics {
+    type Value = EmbeddedLyrics;
+}
+
+// synchronized lyrics from SYLT frames are preferred over plain ones
+fn lyrics(revision: &MetadataRevision) -> Option<String> {
+    let tags = revision
+        .tags()
+        .iter()
+        .filter(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+        .collect::<Vec<_>>();
+
+    tags.iter()
+        .find(|tag| tag.key.starts_with("SYLT"))
+        .or_else(|| tags.first())
+        .map(|tag| tag.value.to_string())
+}
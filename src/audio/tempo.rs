@@ -0,0 +1,110 @@
+use super::filters::{Effect, Frame};
+
+// lengths in milliseconds, tuned for music like in SoundTouch
+const SEQUENCE_MS: u32 = 80;
+const SEEK_WINDOW_MS: u32 = 20;
+const OVERLAP_MS: u32 = 10;
+
+// changes speed without changing pitch using WSOLA, every sequence of the input is
+// placed where it best matches the end of the previous one and crossfaded with it
+pub struct TimeStretch {
+    tempo: f64,
+    sequence: usize,
+    seek_window: usize,
+    overlap: usize,
+    input: Vec<Frame>,
+    // end of the previous sequence, crossfaded with the start of the next one
+    tail: Vec<Frame>,
+    // input frames still to be skipped, carried between sequences
+    skip: usize,
+    skip_fract: f64,
+    output: Vec<Frame>,
+}
+
+impl TimeStretch {
+    pub fn new(sample_rate: u32, tempo: f64) -> Self {
+        let frames = |ms: u32| (sample_rate * ms / 1000) as usize;
+
+        TimeStretch {
+            tempo,
+            sequence: frames(SEQUENCE_MS),
+            seek_window: frames(SEEK_WINDOW_MS),
+            overlap: frames(OVERLAP_MS),
+            input: Vec::new(),
+            tail: Vec::new(),
+            skip: 0,
+            skip_fract: 0.0,
+            output: Vec::new(),
+        }
+    }
+
+    // offset in the seek window where the input is most similar to the tail
+    fn best_offset(&self) -> usize {
+        if self.tail.is_empty() {
+            return 0;
+        }
+
+        let mut best = (0, f32::MIN);
+        for offset in 0..self.seek_window {
+            let window = &self.input[offset..offset + self.overlap];
+
+            let (mut corr, mut norm) = (0.0, 0.0);
+            for (a, b) in window.iter().zip(&self.tail) {
+                let (a, b) = (a[0] + a[1], b[0] + b[1]);
+                corr += a * b;
+                norm += a * a;
+            }
+
+            let score = corr / norm.max(1e-9).sqrt();
+            if score > best.1 {
+                best = (offset, score);
+            }
+        }
+
+        best.0
+    }
+}
+
+impl Effect for TimeStretch {
+    fn process(&mut self, frames: &mut Vec<Frame>) {
+        self.input.extend_from_slice(frames);
+        self.output.clear();
+
+        // every iteration outputs sequence - overlap frames and consumes tempo times as many
+        loop {
+            let skip = self.skip.min(self.input.len());
+            self.input.drain(..skip);
+            self.skip -= skip;
+
+            if self.input.len() < self.seek_window + self.sequence {
+                break;
+            }
+
+            let offset = self.best_offset();
+            let start = &self.input[offset..];
+
+            if self.tail.is_empty() {
+                self.output.extend_from_slice(&start[..self.overlap]);
+            } else {
+                for (i, (new, old)) in start.iter().zip(&self.tail).enumerate() {
+                    let fade = i as f32 / self.overlap as f32;
+                    self.output.push([
+                        old[0] * (1.0 - fade) + new[0] * fade,
+                        old[1] * (1.0 - fade) + new[1] * fade,
+                    ]);
+                }
+            }
+
+            let end = self.sequence - self.overlap;
+            self.output.extend_from_slice(&start[self.overlap..end]);
+            self.tail.clear();
+            self.tail.extend_from_slice(&start[end..self.sequence]);
+
+            let skip = self.tempo * end as f64 + self.skip_fract;
+            self.skip = skip as usize;
+            self.skip_fract = skip.fract();
+        }
+
+        std::mem::swap(frames, &mut self.output);
+    }
+}
@@ -0,0 +1,54 @@
+use super::*;
+use crate::commands::play::{enqueue, PlayContext};
+use songbird::tracks::TrackHandle;
+use sources::{Metadata, TrackSource, YoutubeSource};
+use std::sync::atomic::Ordering;
+
+// toggles continuing with related songs when the queue ends
+#[poise::command(prefix_command, guild_only, aliases("radio"))]
+pub async fn autoplay(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let enabled = !guild.autoplay.fetch_xor(true, Ordering::Relaxed);
+
+    let state = if enabled { "enabled" } else { "disabled" };
+    ctx.say(format!("Autoplay {state}")).await?;
+
+    Ok(())
+}
+
+// queues a song related to the one that ended, if nothing else is queued after it
+pub(super) async fn autoplay_next(ctx: &PlayContext, ended: &TrackHandle, metadata: &Metadata) {
+    let queue = ctx.call.lock().await.queue().current_queue();
+    if queue.iter().any(|track| track.uuid() != ended.uuid()) {
+        return;
+    }
+
+    // songs played recently are skipped unless all of them were
+    let url = {
+        let history = ctx.guild.history.lock().unwrap();
+
+        metadata
+            .related
+            .iter()
+            .find(|url| !history.contains(url))
+            .or(metadata.related.first())
+            .cloned()
+    };
+    let Some(url) = url else {
+        return;
+    };
+
+    let source = YoutubeSource::new(
+        &url,
+        ctx.caches.clone(),
+        ctx.network.clone(),
+        ctx.guild.breaker.clone(),
+    );
+    let Ok(mut source) = source.await else {
+        return;
+    };
+    if let Some(Ok(track)) = source.next().await {
+        enqueue(ctx, track).await;
+    }
+}
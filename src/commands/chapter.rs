@@ -0,0 +1,114 @@
+use super::*;
+use crate::audio::track_position;
+use play::format_duration;
+
+// discord refuses longer messages
+const MESSAGE_LEN: usize = 2000;
+use sources::Metadata;
+
+// seeks to a chapter by its number or to the next one, without arguments lists them
+#[poise::command(prefix_command, guild_only, aliases("chapters"))]
+pub async fn chapter(ctx: Context<'_>, target: Option<String>) -> Result<(), Error> {
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let Some(call) = songbird.get(ctx.guild_id().unwrap()) else {
+        ctx.say("Not in a channel").await?;
+
+        return Ok(());
+    };
+
+    let Some(track) = call.lock().await.queue().current() else {
+        ctx.say("Nothing playing").await?;
+
+        return Ok(());
+    };
+
+    let chapters = track
+        .typemap()
+        .read()
+        .await
+        .get::<Metadata>()
+        .map(|metadata| metadata.chapters.clone())
+        .unwrap_or_default();
+
+    if chapters.is_empty() {
+        ctx.say("This song has no chapters").await?;
+
+        return Ok(());
+    }
+
+    let position = track_position(&track).await?;
+
+    let index = match target.as_deref() {
+        None => {
+            let current = chapters
+                .iter()
+                .rposition(|chapter| chapter.start <= position);
+
+            let lines = chapters
+                .iter()
+                .enumerate()
+                .map(|(i, chapter)| {
+                    let line = format!(
+                        "{}. `{}` {}",
+                        i + 1,
+                        format_duration(chapter.start),
+                        chapter.title
+                    );
+
+                    if Some(i) == current {
+                        format!("**{line}**")
+                    } else {
+                        line
+                    }
+                })
+                .collect::<Vec<_>>();
+            ctx.say(join_lines(&lines)).await?;
+
+            return Ok(());
+        }
+        Some("next") => chapters.iter().position(|chapter| chapter.start > position),
+        Some(number) => number
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .filter(|index| *index < chapters.len()),
+    };
+
+    let Some(index) = index else {
+        ctx.say("No such chapter").await?;
+
+        return Ok(());
+    };
+
+    let chapter = &chapters[index];
+    match track.seek_async(chapter.start).await {
+        Ok(_) => {
+            ctx.say(format!("Chapter {}: **{}**", index + 1, chapter.title))
+                .await?
+        }
+        Err(_err) => ctx.say("Couldn't seek to the chapter").await?,
+    };
+
+    Ok(())
+}
+
+// as many lines as fit in a message, with how many were left out
+fn join_lines(lines: &[String]) -> String {
+    let mut text = String::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let rest = format!("...and {} more", lines.len() - i);
+        if text.len() + line.len() + 1 + rest.len() > MESSAGE_LEN {
+            text.push_str(&rest);
+            break;
+        }
+
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text
+}
@@ -0,0 +1,232 @@
+use super::*;
+use crate::commands::play::PlayContext;
+use crate::commands::previous::play_previous;
+use poise::serenity_prelude as serenity;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage,
+};
+use serenity::{ButtonStyle, ComponentInteraction, ReactionType};
+use songbird::tracks::{LoopState, PlayMode};
+
+// custom ids of the buttons start with it, so other components are left alone
+const ID_PREFIX: &str = "player:";
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Previous,
+    Pause,
+    Skip,
+    Stop,
+    Loop,
+    Shuffle,
+    VolumeDown,
+    VolumeUp,
+}
+
+impl Control {
+    const ALL: [Control; 8] = [
+        Control::Previous,
+        Control::Pause,
+        Control::Skip,
+        Control::Stop,
+        Control::Loop,
+        Control::Shuffle,
+        Control::VolumeDown,
+        Control::VolumeUp,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Control::Previous => "previous",
+            Control::Pause => "pause",
+            Control::Skip => "skip",
+            Control::Stop => "stop",
+            Control::Loop => "loop",
+            Control::Shuffle => "shuffle",
+            Control::VolumeDown => "volume_down",
+            Control::VolumeUp => "volume_up",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Control> {
+        let name = id.strip_prefix(ID_PREFIX)?;
+
+        Control::ALL
+            .into_iter()
+            .find(|control| control.name() == name)
+    }
+
+    fn button(&self, emoji: &str) -> CreateButton {
+        CreateButton::new(format!("{ID_PREFIX}{}", self.name()))
+            .emoji(ReactionType::Unicode(emoji.to_string()))
+            .style(ButtonStyle::Secondary)
+    }
+}
+
+// what the buttons show about the player
+pub(super) struct PlayerState {
+    pub paused: bool,
+    pub looping: bool,
+    pub volume: f32,
+}
+
+pub(super) fn player_buttons(state: &PlayerState) -> Vec<CreateActionRow> {
+    let pause = match state.paused {
+        true => Control::Pause.button("▶️").style(ButtonStyle::Success),
+        false => Control::Pause.button("⏸️"),
+    };
+    let looping = match state.looping {
+        true => Control::Loop.button("🔁").style(ButtonStyle::Success),
+        false => Control::Loop.button("🔁"),
+    };
+
+    // shows the volume, pressing it does nothing
+    let volume = CreateButton::new(format!("{ID_PREFIX}volume"))
+        .label(format!("{:.0}%", state.volume * 100.0))
+        .style(ButtonStyle::Secondary)
+        .disabled(true);
+
+    vec![
+        CreateActionRow::Buttons(vec![
+            Control::Previous.button("⏮️"),
+            pause,
+            Control::Skip.button("⏭️"),
+            Control::Stop.button("⏹️").style(ButtonStyle::Danger),
+        ]),
+        CreateActionRow::Buttons(vec![
+            looping,
+            Control::Shuffle.button("🔀"),
+            Control::VolumeDown.button("🔉"),
+            volume,
+            Control::VolumeUp.button("🔊"),
+        ]),
+    ]
+}
+
+// buttons under the now playing message, they work like the commands in the same guild
+pub async fn handle_component(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(control) = Control::from_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(());
+    };
+
+    let songbird = get_songbird(ctx).await.expect("Songbird not registered");
+
+    let Some(call) = songbird.get(guild_id) else {
+        return reply(ctx, interaction, "Not in a channel").await;
+    };
+
+    let guild = data.guild(guild_id);
+    let queue = call.lock().await.queue().clone();
+
+    let info = match queue.current() {
+        Some(track) => track.get_info().await.ok(),
+        None => None,
+    };
+    let mut state = PlayerState {
+        paused: info
+            .as_ref()
+            .is_some_and(|info| info.playing == PlayMode::Pause),
+        looping: info
+            .as_ref()
+            .is_some_and(|info| info.loops != LoopState::Finite(0)),
+        volume: guild.filters.settings().volume,
+    };
+
+    match control {
+        Control::Previous => {
+            // looking the track up takes longer than an answer is waited for
+            interaction.defer(&ctx.http).await?;
+
+            let play_ctx = PlayContext::from_parts(
+                ctx,
+                data,
+                guild_id,
+                interaction.channel_id,
+                interaction.user.clone(),
+                call,
+            )
+            .await;
+
+            if let Err(reason) = play_previous(&play_ctx).await {
+                let followup = CreateInteractionResponseFollowup::new()
+                    .content(reason)
+                    .ephemeral(true);
+                interaction.create_followup(&ctx.http, followup).await?;
+            }
+
+            return Ok(());
+        }
+        Control::Skip | Control::Stop => {
+            let result = match control {
+                Control::Skip => service::skip(ctx, data, guild_id).await,
+                _ => service::stop(ctx, data, guild_id).await,
+            };
+            if let Err(err) = result {
+                return reply(ctx, interaction, &err.to_string()).await;
+            }
+            (state.paused, state.looping) = (false, false);
+        }
+        Control::Shuffle => {
+            if let Err(err) = service::shuffle(ctx, data, guild_id).await {
+                return reply(ctx, interaction, &err.to_string()).await;
+            }
+        }
+        Control::Pause => {
+            if let Err(err) = service::set_paused(ctx, data, guild_id, !state.paused).await {
+                return reply(ctx, interaction, &err.to_string()).await;
+            }
+            state.paused = !state.paused;
+        }
+        Control::Loop => {
+            if let Err(err) = service::set_looping(ctx, guild_id, !state.looping).await {
+                return reply(ctx, interaction, &err.to_string()).await;
+            }
+            state.looping = !state.looping;
+        }
+        Control::VolumeDown | Control::VolumeUp => {
+            let step = match control {
+                Control::VolumeUp => VOLUME_STEP,
+                _ => -VOLUME_STEP,
+            };
+            // rounded so steps always land on whole percents
+            let volume = ((state.volume + step) * 100.0).round() / 100.0;
+
+            state.volume = service::set_volume(data, guild_id, volume);
+        }
+    }
+
+    let response = CreateInteractionResponseMessage::new().components(player_buttons(&state));
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(response),
+        )
+        .await?;
+
+    Ok(())
+}
+
+// only the member who pressed the button sees it
+async fn reply(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    text: &str,
+) -> Result<(), Error> {
+    let message = CreateInteractionResponseMessage::new()
+        .content(text)
+        .ephemeral(true);
+    interaction
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await?;
+
+    Ok(())
+}
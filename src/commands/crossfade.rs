@@ -0,0 +1,37 @@
+use super::*;
+use crate::player::MAX_CROSSFADE;
+use std::time::Duration;
+
+// without arguments shows the current crossfade, 0 turns it off
+#[poise::command(prefix_command, guild_only, aliases("fade"))]
+pub async fn crossfade(ctx: Context<'_>, secs: Option<f32>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let Some(secs) = secs else {
+        let crossfade = guild.transitions.read().unwrap().crossfade;
+        ctx.say(format!("Crossfade is **{}s**", crossfade.as_secs_f32()))
+            .await?;
+
+        return Ok(());
+    };
+
+    if !(0.0..=MAX_CROSSFADE.as_secs_f32()).contains(&secs) {
+        ctx.say(format!(
+            "Crossfade must be between 0 and {}s",
+            MAX_CROSSFADE.as_secs()
+        ))
+        .await?;
+
+        return Ok(());
+    }
+
+    guild.transitions.write().unwrap().crossfade = Duration::from_secs_f32(secs);
+
+    if secs == 0.0 {
+        ctx.say("Crossfade disabled").await?;
+    } else {
+        ctx.say(format!("Crossfade set to **{secs}s**")).await?;
+    }
+
+    Ok(())
+}
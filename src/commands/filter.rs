@@ -0,0 +1,133 @@
+use super::*;
+use crate::audio::{Filter, EQ_BANDS, EQ_MAX_GAIN};
+
+// shows active filters, the filters themselves are toggled with subcommands
+#[poise::command(
+    prefix_command,
+    guild_only,
+    aliases("filters", "fx"),
+    subcommands(
+        "bassboost",
+        "nightcore",
+        "vaporwave",
+        "eight_d",
+        "karaoke",
+        "tremolo",
+        "equalizer",
+        "clear"
+    )
+)]
+pub async fn filter(ctx: Context<'_>) -> Result<(), Error> {
+    let settings = ctx.data().guild(ctx.guild_id().unwrap()).filters.settings();
+
+    if settings.is_flat() {
+        ctx.say("No filters enabled").await?;
+
+        return Ok(());
+    }
+
+    let mut active = settings
+        .enabled
+        .iter()
+        .map(|filter| format!("`{filter}`"))
+        .collect::<Vec<_>>();
+
+    let bands = EQ_BANDS
+        .iter()
+        .zip(settings.equalizer)
+        .filter(|(_, gain)| *gain != 0.0)
+        .map(|(freq, gain)| format!("{freq}Hz {gain:+}dB"))
+        .collect::<Vec<_>>();
+    if !bands.is_empty() {
+        active.push(format!("`equalizer` ({})", bands.join(", ")));
+    }
+
+    ctx.say(format!("Active filters: {}", active.join(", ")))
+        .await?;
+
+    Ok(())
+}
+
+async fn toggle(ctx: Context<'_>, filter: Filter) -> Result<(), Error> {
+    let enabled = ctx
+        .data()
+        .guild(ctx.guild_id().unwrap())
+        .filters
+        .toggle(filter);
+
+    let state = if enabled { "enabled" } else { "disabled" };
+    ctx.say(format!("Filter **`{filter}`** {state}")).await?;
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, guild_only, aliases("bass"))]
+pub async fn bassboost(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::BassBoost).await
+}
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn nightcore(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::Nightcore).await
+}
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn vaporwave(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::Vaporwave).await
+}
+
+#[poise::command(prefix_command, guild_only, rename = "8d")]
+pub async fn eight_d(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::EightD).await
+}
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn karaoke(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::Karaoke).await
+}
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn tremolo(ctx: Context<'_>) -> Result<(), Error> {
+    toggle(ctx, Filter::Tremolo).await
+}
+
+// sets gain of one band, without arguments resets all of them
+#[poise::command(prefix_command, guild_only, aliases("eq"))]
+pub async fn equalizer(
+    ctx: Context<'_>,
+    band: Option<usize>,
+    gain: Option<f32>,
+) -> Result<(), Error> {
+    let filters = ctx.data().guild(ctx.guild_id().unwrap()).filters.clone();
+
+    let (Some(band), Some(gain)) = (band, gain) else {
+        filters.reset_equalizer();
+        ctx.say("Equalizer reset").await?;
+
+        return Ok(());
+    };
+
+    let Some(freq) = band.checked_sub(1).and_then(|index| EQ_BANDS.get(index)) else {
+        ctx.say(format!("Band must be between 1 and {}", EQ_BANDS.len()))
+            .await?;
+
+        return Ok(());
+    };
+
+    filters.set_band(band - 1, gain);
+
+    let gain = gain.clamp(-EQ_MAX_GAIN, EQ_MAX_GAIN);
+    ctx.say(format!("Equalizer band **{freq}Hz** set to **{gain:+}dB**"))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, guild_only, aliases("reset", "off"))]
+pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.data().guild(ctx.guild_id().unwrap()).filters.clear();
+
+    ctx.say("All filters disabled").await?;
+
+    Ok(())
+}
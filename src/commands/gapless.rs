@@ -0,0 +1,17 @@
+use super::*;
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn gapless(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let enabled = {
+        let mut transitions = guild.transitions.write().unwrap();
+        transitions.gapless = !transitions.gapless;
+        transitions.gapless
+    };
+
+    let state = if enabled { "enabled" } else { "disabled" };
+    ctx.say(format!("Gapless playback {state}")).await?;
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use super::*;
+use crate::player::Outcome;
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use serenity::builder::CreateEmbed;
+
+// how many tracks are listed
+const HISTORY_SHOWN: usize = 10;
+
+#[poise::command(prefix_command, guild_only, aliases("recent"))]
+pub async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let lines = guild
+        .history
+        .lock()
+        .unwrap()
+        .recent()
+        .take(HISTORY_SHOWN)
+        .enumerate()
+        .map(|(i, entry)| {
+            let outcome = match entry.outcome {
+                Outcome::Playing => "playing",
+                Outcome::Completed => "played",
+                Outcome::Skipped => "skipped",
+            };
+
+            let title = match entry.metadata.link() {
+                Some(link) => format!("[{}]({link})", entry.metadata.title),
+                None => entry.metadata.title.clone(),
+            };
+
+            format!(
+                "`{}.` {title} - <@{}>, {outcome} <t:{}:R>",
+                i + 1,
+                entry.requester,
+                entry.started,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        ctx.say("Nothing played yet").await?;
+
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Recently played")
+        .description(lines.join("\n"))
+        .color(serenity::Colour::MAGENTA);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use super::*;
+use crate::audio::{track_position, EmbeddedLyrics};
+use crate::commands::play::get_http_client;
+use crate::lyrics::{Lyrics, LyricsProvider};
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use serenity::builder::CreateEmbed;
+use songbird::tracks::TrackHandle;
+use sources::Metadata;
+use std::time::Duration;
+
+// how often synced lyrics check the position of the track
+const SYNC_INTERVAL: Duration = Duration::from_millis(500);
+// lines shown around the current one
+const CONTEXT_LINES: usize = 2;
+
+// shows lyrics of the current song, synced ones follow the playback
+#[poise::command(prefix_command, guild_only)]
+pub async fn lyrics(ctx: Context<'_>) -> Result<(), Error> {
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let Some(call) = songbird.get(ctx.guild_id().unwrap()) else {
+        ctx.say("Not in a channel").await?;
+
+        return Ok(());
+    };
+
+    let Some(track) = call.lock().await.queue().current() else {
+        ctx.say("Nothing playing").await?;
+
+        return Ok(());
+    };
+
+    let (metadata, embedded) = {
+        let typemap = track.typemap().read().await;
+
+        (
+            typemap.get::<Metadata>().cloned(),
+            typemap.get::<EmbeddedLyrics>().cloned(),
+        )
+    };
+
+    let Some(metadata) = metadata else {
+        ctx.say("Nothing playing").await?;
+
+        return Ok(());
+    };
+
+    // tags of the file are more accurate than a search by title
+    let lyrics = match embedded.as_ref().and_then(|lyrics| lyrics.get()) {
+        Some(text) => Lyrics::parse(text),
+        None => {
+            let provider = LyricsProvider::new(
+                get_http_client(ctx).await,
+                ctx.data().config.lyrics_url.clone(),
+            );
+
+            provider.search(&metadata.title).await?
+        }
+    };
+
+    match lyrics {
+        Some(Lyrics::Synced(lines)) => follow(ctx, &track, &metadata, &lines).await,
+        Some(lyrics) => {
+            let pages = lyrics.pages();
+            let pages = pages.iter().map(String::as_str).collect::<Vec<_>>();
+
+            poise::builtins::paginate(ctx, &pages).await?;
+
+            Ok(())
+        }
+        None => {
+            ctx.say(format!("No lyrics found for **{}**", metadata.title))
+                .await?;
+
+            Ok(())
+        }
+    }
+}
+
+// keeps editing the message with the line being sung until the track ends
+async fn follow(
+    ctx: Context<'_>,
+    track: &TrackHandle,
+    metadata: &Metadata,
+    lines: &[(Duration, String)],
+) -> Result<(), Error> {
+    let embed = |current| {
+        let embed = CreateEmbed::new()
+            .title(&metadata.title)
+            .description(window(lines, current));
+
+        match metadata.link() {
+            Some(link) => embed.url(link),
+            None => embed,
+        }
+    };
+
+    let mut current = current_line(lines, track_position(track).await?);
+    let message = ctx
+        .send(CreateReply::default().embed(embed(current)))
+        .await?;
+
+    // the track's state can't be read anymore once it has ended
+    while track
+        .get_info()
+        .await
+        .is_ok_and(|state| !state.playing.is_done())
+    {
+        let line = current_line(lines, track_position(track).await?);
+
+        if line != current {
+            current = line;
+            message
+                .edit(ctx, CreateReply::default().embed(embed(current)))
+                .await?;
+        }
+
+        tokio::time::sleep(SYNC_INTERVAL).await;
+    }
+
+    Ok(())
+}
+
+// index of the last line that started before the position
+fn current_line(lines: &[(Duration, String)], position: Duration) -> Option<usize> {
+    lines
+        .partition_point(|(time, _)| *time <= position)
+        .checked_sub(1)
+}
+
+fn window(lines: &[(Duration, String)], current: Option<usize>) -> String {
+    let center = current.unwrap_or(0);
+    let start = center.saturating_sub(CONTEXT_LINES);
+    let end = (center + CONTEXT_LINES + 1).min(lines.len());
+
+    (start..end)
+        .map(|i| match &lines[i].1 {
+            line if line.is_empty() => "♪".to_string(),
+            line if Some(i) == current => format!("**{line}**"),
+            line => line.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
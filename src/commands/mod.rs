@@ -1,16 +1,48 @@
 use songbird::serenity::get as get_songbird;
+use std::sync::Arc;
 
-pub type Data = (); // User data, which is stored and accessible in all command invocations
+pub use crate::state::Data;
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
-pub type Context<'a> = poise::Context<'a, Data, Error>;
+pub type Context<'a> = poise::Context<'a, Arc<Data>, Error>;
 
+mod autoplay;
+mod chapter;
+mod controls;
+mod crossfade;
+mod filter;
+mod gapless;
+mod history;
 mod join;
+mod lyrics;
+mod pitch;
 mod play;
+mod playlist;
+mod previous;
+mod queue;
 mod seek;
+pub mod service;
 mod skip;
-mod sources;
+mod source;
+pub mod sources;
+mod speed;
+mod stats;
 
+pub use autoplay::autoplay;
+pub use chapter::chapter;
+pub use controls::handle_component;
+pub use crossfade::crossfade;
+pub use filter::filter;
+pub use gapless::gapless;
+pub use history::history;
 pub use join::join;
+pub use lyrics::lyrics;
+pub use pitch::pitch;
 pub use play::play;
+pub use playlist::playlist;
+pub use previous::previous;
+pub use queue::queue;
 pub use seek::seek;
 pub use skip::skip;
+pub use source::source;
+pub use speed::speed;
+pub use stats::stats;
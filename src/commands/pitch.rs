@@ -0,0 +1,24 @@
+use super::*;
+use crate::audio::MAX_PITCH;
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn pitch(ctx: Context<'_>, semitones: f32) -> Result<(), Error> {
+    if !(-MAX_PITCH..=MAX_PITCH).contains(&semitones) {
+        ctx.say(format!(
+            "Pitch must be between -{MAX_PITCH} and {MAX_PITCH} semitones"
+        ))
+        .await?;
+
+        return Ok(());
+    }
+
+    ctx.data()
+        .guild(ctx.guild_id().unwrap())
+        .filters
+        .set_pitch(semitones);
+
+    ctx.say(format!("Pitch set to **{semitones:+}** semitones"))
+        .await?;
+
+    Ok(())
+}
@@ -1,64 +1,215 @@
 use super::*;
+use crate::audio::{DspCompose, EmbeddedLyrics, LoudnessCache, Normalization, PlaybackClock};
+use crate::commands::autoplay::autoplay_next;
+use crate::commands::controls::{player_buttons, PlayerState};
 use crate::commands::join::join_channel;
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::network::Network;
+use crate::player::{
+    ClipStopper, PlayerEvent, PlayerEvents, Presence, SegmentSkipper, StatusTarget,
+    TransitionHandler,
+};
+use crate::sponsorblock::SponsorBlock;
+use crate::state::GuildState;
 use crate::{HttpClient, HttpKey};
 use poise::serenity_prelude as serenity;
 use serenity::async_trait;
-use serenity::builder::{CreateEmbed, CreateEmbedFooter, CreateMessage};
+use serenity::builder::{
+    CreateActionRow, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage,
+};
 use songbird::{
     events::{Event, EventHandler},
     serenity::get as get_songbird,
-    tracks::Track,
-    EventContext, TrackEvent,
+    tracks::{PlayMode, Track, TrackHandle},
+    Call, EventContext, TrackEvent,
+};
+use sources::{
+    parse_timestamp, Metadata, PlaylistFileSource, PlaylistFormat, SourceCaches, SourceError,
+    SourceFactory, SourceKind, TrackInfo, TrackSource, YoutubeUrl,
 };
-use sources::{Metadata, SourceFactory, TrackInfo};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, info_span, warn, Span};
 
+// when the circuit breaker of the guild is open
+const UNAVAILABLE_MESSAGE: &str = "YouTube is rejecting the bot's requests, try again in a minute";
+
+// plays the query, or a playlist file attached to the message
 #[poise::command(prefix_command, guild_only, aliases("p"))]
-pub async fn play(ctx: Context<'_>, #[rest] msg: String) -> Result<(), Error> {
-    let guild_id = ctx.guild_id().unwrap();
+pub async fn play(ctx: Context<'_>, #[rest] msg: Option<String>) -> Result<(), Error> {
+    let (msg, clip) = match Clip::parse(msg.as_deref().unwrap_or_default()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            ctx.say(err).await?;
 
-    let songbird = get_songbird(ctx.serenity_context())
-        .await
-        .expect("Songbird not registered");
+            return Ok(());
+        }
+    };
+
+    let attachment = playlist_attachment(ctx);
+    if msg.is_empty() && attachment.is_none() {
+        ctx.say("Nothing to play").await?;
+
+        return Ok(());
+    }
+
+    let Some(call) = connect(ctx).await? else {
+        return Ok(());
+    };
+
+    let factory = source_factory(ctx).await;
+    let tracks_source = match attachment {
+        Some((attachment, format)) => {
+            ctx.say(format!("Loading playlist: **`{}`**", attachment.filename))
+                .await?;
 
-    // get voice connection, if doesnt exist, join channel and try again
-    let call = match songbird.get(guild_id) {
-        Some(call) => call,
+            let client = get_http_client(ctx).await;
+            PlaylistFileSource::open(&attachment.url, format, None, &client, factory.clone())
+                .await
+                .map(|source| factory.measure(&attachment.filename, source))
+        }
         None => {
-            join_channel(ctx).await?;
-            let Some(call) = songbird.get(guild_id) else {
-                return Ok(());
-            };
-            call
+            ctx.say(format!("Searching: **`{msg}`**")).await?;
+
+            factory.create(&msg).await
         }
     };
+    let tracks_source = match tracks_source {
+        Ok(tracks_source) => tracks_source,
+        Err(SourceError::Unavailable) => {
+            ctx.say(UNAVAILABLE_MESSAGE).await?;
 
-    ctx.say(format!("Searching: **`{msg}`**")).await?;
+            return Ok(());
+        }
+        Err(_) => {
+            ctx.say("Error while creating source").await?;
 
-    let http_client = get_http_client(ctx).await;
+            return Ok(());
+        }
+    };
 
-    let Ok(mut tracks_source) = SourceFactory::new(&msg, http_client).await else {
-        ctx.say("Error while creating source").await?;
+    play_clip(ctx, &call, tracks_source, clip).await
+}
 
-        return Ok(());
+// first file of the command message that is a playlist
+fn playlist_attachment(ctx: Context<'_>) -> Option<(serenity::Attachment, PlaylistFormat)> {
+    let Context::Prefix(ctx) = ctx else {
+        return None;
     };
 
+    ctx.msg.attachments.iter().find_map(|attachment| {
+        let format = PlaylistFormat::from_name(&attachment.filename)?;
+
+        Some((attachment.clone(), format))
+    })
+}
+
+// part of a track given with --from and --to, like "--from 1:30 --to 3:00"
+#[derive(Default)]
+struct Clip {
+    from: Option<Duration>,
+    to: Option<Duration>,
+}
+
+impl Clip {
+    // splits the options from the query
+    fn parse(msg: &str) -> Result<(String, Clip), String> {
+        let mut clip = Clip::default();
+        let mut query = Vec::new();
+        let mut words = msg.split_whitespace();
+
+        while let Some(word) = words.next() {
+            let bound = match word {
+                "--from" => &mut clip.from,
+                "--to" => &mut clip.to,
+                _ => {
+                    query.push(word);
+                    continue;
+                }
+            };
+
+            let time = words.next().unwrap_or_default();
+            *bound = Some(parse_timestamp(time).ok_or(format!("Invalid time: **`{time}`**"))?);
+        }
+
+        if let (Some(from), Some(to)) = (clip.from, clip.to) {
+            if to <= from {
+                return Err("The end has to be after the start".to_string());
+            }
+        }
+
+        Ok((query.join(" "), clip))
+    }
+
+    // replaces the start from the link, live streams can't be clipped
+    fn apply(self, track: &mut TrackInfo) {
+        if track.metadata.is_live {
+            return;
+        }
+
+        track.start = self.from.or(track.start);
+        track.end = self.to;
+    }
+}
+
+// get voice connection, if doesnt exist, join channel and try again
+pub(super) async fn connect(ctx: Context<'_>) -> Result<Option<Arc<Mutex<Call>>>, Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    if let Some(call) = songbird.get(guild_id) {
+        return Ok(Some(call));
+    }
+
+    join_channel(ctx).await?;
+
+    Ok(songbird.get(guild_id))
+}
+
+// enqueues every track of the source
+pub(super) async fn play_source(
+    ctx: Context<'_>,
+    call: &Arc<Mutex<Call>>,
+    tracks_source: Box<dyn TrackSource>,
+) -> Result<(), Error> {
+    play_clip(ctx, call, tracks_source, Clip::default()).await
+}
+
+// like play_source, the clip is only applied to the first track
+async fn play_clip(
+    ctx: Context<'_>,
+    call: &Arc<Mutex<Call>>,
+    mut tracks_source: Box<dyn TrackSource>,
+    clip: Clip,
+) -> Result<(), Error> {
+    let play_ctx = PlayContext::new(ctx, call.clone()).await;
+    let mut clip = Some(clip);
+
     while let Some(track) = tracks_source.next().await {
-        let Ok(TrackInfo { metadata, input }) = track else {
-            ctx.say("Error during fetching one of songs").await?;
-            continue;
+        let mut track = match track {
+            Ok(track) => track,
+            Err(err) if err.ends_source() => {
+                ctx.say(UNAVAILABLE_MESSAGE).await?;
+                break;
+            }
+            Err(_) => {
+                ctx.say("Error during fetching one of songs").await?;
+                continue;
+            }
         };
 
-        // pausing track so when added to queue it sends start playing event
-        let track = Track::from(input).pause();
-
-        let event_handler = TrackHandler::new(ctx, metadata.clone()).await;
-        let handler = call.lock().await.enqueue(track).await;
+        if let Some(clip) = clip.take() {
+            clip.apply(&mut track);
+        }
 
-        // add handler that sends message when song starts playing
-        handler
-            .add_event(Event::Track(TrackEvent::Play), event_handler)
-            .unwrap();
+        let metadata = track.metadata.clone();
+        enqueue(&play_ctx, track).await;
 
         // send queue message if there is already playing song
         let driver = call.lock().await;
@@ -79,10 +230,214 @@ pub async fn play(ctx: Context<'_>, #[rest] msg: String) -> Result<(), Error> {
     Ok(())
 }
 
-async fn get_http_client(ctx: Context<'_>) -> HttpClient {
+// what queued tracks need from the command that added them, kept for their events
+#[derive(Clone)]
+pub(super) struct PlayContext {
+    pub config: Arc<Config>,
+    pub loudness: LoudnessCache,
+    pub guild: Arc<GuildState>,
+    pub call: Arc<Mutex<Call>>,
+    pub client: HttpClient,
+    pub caches: SourceCaches,
+    pub network: Arc<Network>,
+    presence: Presence,
+    metrics: Metrics,
+    events: PlayerEvents,
+    guild_id: serenity::GuildId,
+    http: Arc<serenity::Http>,
+    shard: serenity::ShardMessenger,
+    channel: serenity::ChannelId,
+    author: serenity::User,
+}
+
+impl PlayContext {
+    pub(super) async fn new(ctx: Context<'_>, call: Arc<Mutex<Call>>) -> Self {
+        PlayContext::from_parts(
+            ctx.serenity_context(),
+            ctx.data(),
+            ctx.guild_id().unwrap(),
+            ctx.channel_id(),
+            ctx.author().clone(),
+            call,
+        )
+        .await
+    }
+
+    // for things that aren't commands, like pressed buttons
+    pub(super) async fn from_parts(
+        ctx: &serenity::Context,
+        data: &Data,
+        guild_id: serenity::GuildId,
+        channel: serenity::ChannelId,
+        author: serenity::User,
+        call: Arc<Mutex<Call>>,
+    ) -> Self {
+        PlayContext {
+            config: data.config.clone(),
+            loudness: data.loudness.clone(),
+            guild: data.guild(guild_id),
+            call,
+            client: http_client(ctx).await,
+            caches: data.caches.clone(),
+            network: data.network.clone(),
+            presence: data.presence.clone(),
+            metrics: data.metrics.clone(),
+            events: data.events.clone(),
+            guild_id,
+            http: Arc::clone(&ctx.http),
+            shard: ctx.shard.clone(),
+            channel,
+            author,
+        }
+    }
+
+    async fn status_target(&self) -> StatusTarget<'_> {
+        let voice_channel = self.call.lock().await.current_channel();
+
+        StatusTarget {
+            guild: self.guild_id,
+            voice_channel: voice_channel.map(|id| serenity::ChannelId::new(id.0.get())),
+            shard: &self.shard,
+            http: &self.http,
+        }
+    }
+
+    // sources for queries of the guild, the same as its commands use
+    pub(super) fn source_factory(&self) -> SourceFactory {
+        SourceFactory::new(
+            self.client.clone(),
+            *self.guild.default_source.read().unwrap(),
+            self.config.local_dir.clone(),
+            self.caches.clone(),
+            self.metrics.clone(),
+            self.network.clone(),
+            self.guild.breaker.clone(),
+        )
+    }
+}
+
+// wraps the input with the guild's audio processing and adds it to the queue
+pub(super) async fn enqueue(ctx: &PlayContext, track: TrackInfo) -> TrackHandle {
+    let TrackInfo {
+        metadata,
+        input,
+        start,
+        end,
+    } = track;
+    let guild = &ctx.guild;
+    guild.stopped.store(false, Ordering::Relaxed);
+
+    // decode through the guild's filters so they can be switched mid track
+    let normalization = ctx.config.loudness_target.map(|target| Normalization {
+        target,
+        key: metadata.url.clone(),
+        cache: ctx.loudness.clone(),
+    });
+
+    let clock = PlaybackClock::default();
+    if let Some(end) = end {
+        clock.set_clip_end(end);
+    }
+    let lyrics = EmbeddedLyrics::default();
+    let input = DspCompose::wrap(
+        input,
+        guild.filters.clone(),
+        clock.clone(),
+        normalization,
+        lyrics.clone(),
+    );
+
+    // pausing track so when added to queue it sends start playing event
+    let track = Track::from(input).pause();
+
+    // events of the track from queueing to its end
+    let span = info_span!(
+        "track",
+        guild = ctx.guild_id.get(),
+        title = metadata.title,
+        url = metadata.url,
+        source = %metadata.kind,
+    );
+    info!(parent: &span, "track queued");
+    ctx.events.send(PlayerEvent::TrackQueued {
+        guild: ctx.guild_id.to_string(),
+        title: metadata.title.clone(),
+        url: metadata.url.clone(),
+    });
+
+    let event_handler = TrackHandler {
+        ctx: ctx.clone(),
+        metadata: metadata.clone(),
+        clock: clock.clone(),
+        span,
+    };
+    // only youtube videos have sponsor segments
+    let video_id = (metadata.kind == SourceKind::Youtube && !metadata.is_live)
+        .then(|| YoutubeUrl::parse(&metadata.url)?.video_id)
+        .flatten();
+
+    let handler = ctx.call.lock().await.enqueue(track).await;
+    {
+        let mut typemap = handler.typemap().write().await;
+        typemap.insert::<PlaybackClock>(clock.clone());
+        typemap.insert::<EmbeddedLyrics>(lyrics);
+        typemap.insert::<Metadata>(metadata);
+    }
+
+    // crossfades into or preloads the track queued after this one
+    let queue = ctx.call.lock().await.queue().clone();
+    TransitionHandler::new(guild.clone(), queue, clock.clone()).register(&handler);
+    if let Some(end) = end {
+        ClipStopper::new(guild.clone(), clock.clone(), end).register(&handler, start);
+    }
+
+    if let Some(url) = &ctx.config.sponsorblock_url {
+        if let Some(video_id) = video_id {
+            let provider = SponsorBlock::new(ctx.client.clone(), url.clone());
+            SegmentSkipper::new(provider, video_id, clock, ctx.http.clone(), ctx.channel)
+                .register(&handler);
+        }
+    }
+
+    // add handler that sends message when song starts playing and records it
+    handler
+        .add_event(Event::Track(TrackEvent::Play), event_handler.clone())
+        .unwrap();
+    handler
+        .add_event(Event::Track(TrackEvent::End), event_handler)
+        .unwrap();
+
+    // tracks from links with a timestamp or clips start from there
+    if let Some(start) = start {
+        let _ = handler.seek(start);
+    }
+
+    handler
+}
+
+// sources for queries of the guild the command was used in
+pub(super) async fn source_factory(ctx: Context<'_>) -> SourceFactory {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+    let default = *guild.default_source.read().unwrap();
+
+    SourceFactory::new(
+        get_http_client(ctx).await,
+        default,
+        ctx.data().config.local_dir.clone(),
+        ctx.data().caches.clone(),
+        ctx.data().metrics.clone(),
+        ctx.data().network.clone(),
+        guild.breaker.clone(),
+    )
+}
+
+pub(super) async fn get_http_client(ctx: Context<'_>) -> HttpClient {
+    http_client(ctx.serenity_context()).await
+}
+
+async fn http_client(ctx: &serenity::Context) -> HttpClient {
     // reqwest Client for rusty_ytdl
-    ctx.serenity_context()
-        .data
+    ctx.data
         .read()
         .await
         .get::<HttpKey>()
@@ -95,13 +450,41 @@ struct EmbedInfo;
 
 impl EmbedInfo {
     fn create_embed(metadata: Metadata, text: &str, author: serenity::User) -> CreateEmbed {
-        CreateEmbed::new()
-            .title(metadata.title)
-            .url(metadata.url)
+        let duration = match metadata.duration {
+            _ if metadata.is_live => "🔴 LIVE".to_string(),
+            Some(duration) => format_duration(duration),
+            None => "Unknown".to_string(),
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title(&metadata.title)
             .description(format!("**{text}**"))
-            .thumbnail(metadata.thumbnail)
             .color(serenity::Colour::MAGENTA)
-            .footer(EmbedInfo::create_footer(author))
+            .field("Duration", duration, true)
+            .field("Source", metadata.kind.to_string(), true);
+
+        if let Some(link) = metadata.link() {
+            embed = embed.url(link);
+        }
+        if !metadata.thumbnail.is_empty() {
+            embed = embed.thumbnail(metadata.thumbnail);
+        }
+
+        if let Some(artist) = metadata.artist {
+            let artist = match metadata.artist_url {
+                Some(url) => format!("[{artist}]({url})"),
+                None => artist,
+            };
+            embed = embed.field("Channel", artist, true);
+        }
+        if let Some(views) = metadata.views {
+            embed = embed.field("Views", format_count(views), true);
+        }
+        if let Some(date) = metadata.upload_date {
+            embed = embed.field("Uploaded", date, true);
+        }
+
+        embed.footer(EmbedInfo::create_footer(author))
     }
     fn create_footer(author: serenity::User) -> CreateEmbedFooter {
         CreateEmbedFooter::new(author.name.clone())
@@ -110,40 +493,138 @@ impl EmbedInfo {
     }
 }
 
-// handler that sends message when the song starts playing
-struct TrackHandler {
-    metadata: Metadata,
-    http: Arc<serenity::Http>,
-    channel: serenity::ChannelId,
-    author: serenity::User,
+// h:mm:ss, or m:ss for tracks shorter than an hour
+pub(super) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}:{mins:02}:{secs:02}")
+    } else {
+        format!("{mins}:{secs:02}")
+    }
 }
 
-impl TrackHandler {
-    async fn new(ctx: Context<'_>, metadata: Metadata) -> Self {
-        TrackHandler {
-            metadata,
-            http: Arc::clone(&ctx.serenity_context().http),
-            channel: ctx.channel_id(),
-            author: ctx.author().clone(),
+// 1234567 as 1,234,567
+fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
         }
+        formatted.push(digit);
     }
+
+    formatted
+}
+
+// handler that sends message when the song starts playing and keeps the history
+#[derive(Clone)]
+struct TrackHandler {
+    ctx: PlayContext,
+    metadata: Metadata,
+    clock: PlaybackClock,
+    span: Span,
 }
 
 #[async_trait]
 impl EventHandler for TrackHandler {
-    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
-        let _ = self
-            .channel
-            .send_message(
-                self.http.clone(),
-                CreateMessage::new().embed(EmbedInfo::create_embed(
-                    self.metadata.clone(),
-                    "is playing",
-                    self.author.clone(),
-                )),
-            )
-            .await;
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(&[(state, handle)]) = ctx else {
+            return None;
+        };
+        let guild = &self.ctx.guild;
+
+        if state.playing.is_done() {
+            // skipped tracks are stopped instead of ending on their own, clips are
+            // stopped too once they get to their end
+            let completed = matches!(state.playing, PlayMode::End) || self.clock.clip_ended();
+            // errored tracks fire the end event too
+            if let PlayMode::Errored(err) = &state.playing {
+                error!(parent: &self.span, error = %err, "track failed");
+                self.ctx.metrics.decode_error();
+            } else {
+                info!(parent: &self.span, completed, "track ended");
+            }
+            self.ctx.events.send(PlayerEvent::TrackEnded {
+                guild: self.ctx.guild_id.to_string(),
+                title: self.metadata.title.clone(),
+                url: self.metadata.url.clone(),
+                completed,
+            });
+            self.ctx.metrics.track_played();
+            guild.history.lock().unwrap().finish(handle, completed);
+
+            // only when the queue ran out, not when it was stopped
+            if guild.autoplay.load(Ordering::Relaxed) && !guild.stopped.load(Ordering::Relaxed) {
+                autoplay_next(&self.ctx, handle, &self.metadata).await;
+            }
+
+            let queue = self.ctx.call.lock().await.queue().current_queue();
+            let queue_ended = queue.iter().all(|track| track.uuid() == handle.uuid());
+            if self.ctx.config.now_playing_status && queue_ended {
+                let target = self.ctx.status_target().await;
+                self.ctx.presence.stopped(target).await;
+            }
+
+            return None;
+        }
+
+        info!(parent: &self.span, "track started");
+        self.ctx.events.send(PlayerEvent::TrackStarted {
+            guild: self.ctx.guild_id.to_string(),
+            title: self.metadata.title.clone(),
+            url: self.metadata.url.clone(),
+            duration: self.metadata.duration.into(),
+        });
+
+        guild
+            .history
+            .lock()
+            .unwrap()
+            .record(handle, self.metadata.clone(), self.ctx.author.id);
+
+        let embed =
+            EmbedInfo::create_embed(self.metadata.clone(), "is playing", self.ctx.author.clone());
+        let state = PlayerState {
+            paused: false,
+            looping: false,
+            volume: guild.filters.settings().volume,
+        };
+        show_now_playing(&self.ctx, embed, player_buttons(&state)).await;
+
+        if self.ctx.config.now_playing_status {
+            let target = self.ctx.status_target().await;
+            self.ctx
+                .presence
+                .playing(target, &self.metadata.title)
+                .await;
+        }
 
         None
     }
 }
+
+// the guild keeps one now playing message that is edited for every track, a new one is
+// only sent in another channel or when the old one was deleted
+async fn show_now_playing(ctx: &PlayContext, embed: CreateEmbed, buttons: Vec<CreateActionRow>) {
+    let message = *ctx.guild.now_playing.lock().unwrap();
+
+    if let Some((channel, message)) = message.filter(|(channel, _)| *channel == ctx.channel) {
+        let edit = EditMessage::new()
+            .embed(embed.clone())
+            .components(buttons.clone());
+
+        if channel.edit_message(&ctx.http, message, edit).await.is_ok() {
+            return;
+        }
+    }
+
+    let message = CreateMessage::new().embed(embed).components(buttons);
+    match ctx.channel.send_message(&ctx.http, message).await {
+        Ok(message) => *ctx.guild.now_playing.lock().unwrap() = Some((ctx.channel, message.id)),
+        Err(err) => warn!(error = %err, "now playing message not sent"),
+    }
+}
@@ -0,0 +1,264 @@
+use super::*;
+use crate::commands::play::{connect, play_source, source_factory};
+use crate::db::{SavedPlaylist, SavedTrack};
+use sources::{Metadata, SavedPlaylistSource};
+
+// playlists saved in the bot, without a subcommand lists them
+#[poise::command(
+    prefix_command,
+    guild_only,
+    aliases("pl", "playlists"),
+    subcommands("save", "load", "add", "remove", "list", "share")
+)]
+pub async fn playlist(ctx: Context<'_>) -> Result<(), Error> {
+    list_playlists(ctx).await
+}
+
+// saves the current queue, replacing the playlist if it exists
+#[poise::command(prefix_command, guild_only)]
+pub async fn save(ctx: Context<'_>, name: String) -> Result<(), Error> {
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let queue = match songbird.get(ctx.guild_id().unwrap()) {
+        Some(call) => call.lock().await.queue().current_queue(),
+        None => Vec::new(),
+    };
+
+    let mut tracks = Vec::new();
+    for track in queue {
+        if let Some(metadata) = track.typemap().read().await.get::<Metadata>() {
+            tracks.push(saved_track(metadata));
+        }
+    }
+
+    if tracks.is_empty() {
+        ctx.say("Nothing in the queue to save").await?;
+
+        return Ok(());
+    }
+
+    let (guild, owner) = (ctx.guild_id().unwrap(), ctx.author().id);
+    let len = tracks.len();
+
+    ctx.data()
+        .db
+        .write(|tables| {
+            match tables.own_playlist_mut(guild, owner, &name) {
+                Some(playlist) => playlist.tracks = tracks,
+                None => tables.playlists.push(SavedPlaylist {
+                    guild,
+                    owner,
+                    name: name.clone(),
+                    shared: false,
+                    tracks,
+                }),
+            }
+
+            Some(())
+        })
+        .await?;
+
+    ctx.say(format!("Saved **{len}** songs to playlist **{name}**"))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, guild_only, aliases("play"))]
+pub async fn load(ctx: Context<'_>, name: String) -> Result<(), Error> {
+    let (guild, user) = (ctx.guild_id().unwrap(), ctx.author().id);
+
+    let playlist = ctx
+        .data()
+        .db
+        .read(|tables| tables.find_playlist(guild, user, &name).cloned());
+    let Some(playlist) = playlist else {
+        ctx.say(format!("No playlist named **{name}**")).await?;
+
+        return Ok(());
+    };
+
+    let Some(call) = connect(ctx).await? else {
+        return Ok(());
+    };
+
+    ctx.say(format!(
+        "Loading playlist **{}** ({} songs)",
+        playlist.name,
+        playlist.tracks.len()
+    ))
+    .await?;
+
+    let factory = source_factory(ctx).await;
+    let source = SavedPlaylistSource::new(playlist.tracks, factory.clone());
+
+    play_source(
+        ctx,
+        &call,
+        factory.measure(&playlist.name, Box::new(source)),
+    )
+    .await
+}
+
+// adds a url or query to the playlist, creating it if needed. It's looked up when the
+// playlist is loaded, links of playlists add all of their songs then
+#[poise::command(prefix_command, guild_only)]
+pub async fn add(ctx: Context<'_>, name: String, #[rest] query: String) -> Result<(), Error> {
+    let (guild, owner) = (ctx.guild_id().unwrap(), ctx.author().id);
+    let tracks = vec![SavedTrack {
+        title: query.clone(),
+        url: query.clone(),
+    }];
+
+    ctx.data()
+        .db
+        .write(|tables| {
+            match tables.own_playlist_mut(guild, owner, &name) {
+                Some(playlist) => playlist.tracks.extend(tracks),
+                None => tables.playlists.push(SavedPlaylist {
+                    guild,
+                    owner,
+                    name: name.clone(),
+                    shared: false,
+                    tracks,
+                }),
+            }
+
+            Some(())
+        })
+        .await?;
+
+    ctx.say(format!("Added **{query}** to playlist **{name}**"))
+        .await?;
+
+    Ok(())
+}
+
+// removes one song by its position, without it the whole playlist
+#[poise::command(prefix_command, guild_only, aliases("delete"))]
+pub async fn remove(ctx: Context<'_>, name: String, position: Option<usize>) -> Result<(), Error> {
+    let (guild, owner) = (ctx.guild_id().unwrap(), ctx.author().id);
+
+    let Some(position) = position else {
+        let removed = ctx
+            .data()
+            .db
+            .write(|tables| tables.remove_playlist(guild, owner, &name).then_some(()))
+            .await?;
+
+        if removed.is_some() {
+            ctx.say(format!("Playlist **{name}** deleted")).await?;
+        } else {
+            ctx.say(format!("You have no playlist named **{name}**"))
+                .await?;
+        }
+
+        return Ok(());
+    };
+
+    let removed = ctx
+        .data()
+        .db
+        .write(|tables| {
+            let playlist = tables.own_playlist_mut(guild, owner, &name)?;
+            let index = position
+                .checked_sub(1)
+                .filter(|i| *i < playlist.tracks.len())?;
+
+            Some(playlist.tracks.remove(index))
+        })
+        .await?;
+
+    match removed {
+        Some(track) => {
+            ctx.say(format!("Removed **{}** from **{name}**", track.title))
+                .await?
+        }
+        None => ctx.say("No such playlist or song").await?,
+    };
+
+    Ok(())
+}
+
+#[poise::command(prefix_command, guild_only)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    list_playlists(ctx).await
+}
+
+// toggles whether other members of the guild can load the playlist
+#[poise::command(prefix_command, guild_only)]
+pub async fn share(ctx: Context<'_>, name: String) -> Result<(), Error> {
+    let (guild, owner) = (ctx.guild_id().unwrap(), ctx.author().id);
+
+    let shared = ctx
+        .data()
+        .db
+        .write(|tables| {
+            let playlist = tables.own_playlist_mut(guild, owner, &name)?;
+            playlist.shared = !playlist.shared;
+
+            Some(playlist.shared)
+        })
+        .await?;
+
+    match shared {
+        Some(true) => {
+            ctx.say(format!("Playlist **{name}** is now shared with the server"))
+                .await?
+        }
+        Some(false) => {
+            ctx.say(format!("Playlist **{name}** is now private"))
+                .await?
+        }
+        None => {
+            ctx.say(format!("You have no playlist named **{name}**"))
+                .await?
+        }
+    };
+
+    Ok(())
+}
+
+async fn list_playlists(ctx: Context<'_>) -> Result<(), Error> {
+    let (guild, user) = (ctx.guild_id().unwrap(), ctx.author().id);
+
+    let lines = ctx.data().db.read(|tables| {
+        tables
+            .visible_playlists(guild, user)
+            .map(|playlist| {
+                let owner = if playlist.owner == user {
+                    if playlist.shared {
+                        " (shared)".to_string()
+                    } else {
+                        String::new()
+                    }
+                } else {
+                    format!(" by <@{}>", playlist.owner)
+                };
+
+                format!(
+                    "**{}** - {} songs{owner}",
+                    playlist.name,
+                    playlist.tracks.len()
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+
+    if lines.is_empty() {
+        ctx.say("No saved playlists").await?;
+    } else {
+        ctx.say(lines.join("\n")).await?;
+    }
+
+    Ok(())
+}
+
+fn saved_track(metadata: &Metadata) -> SavedTrack {
+    SavedTrack {
+        title: metadata.title.clone(),
+        url: metadata.url.clone(),
+    }
+}
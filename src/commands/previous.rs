@@ -0,0 +1,65 @@
+use super::*;
+use crate::commands::play::{enqueue, PlayContext};
+
+// plays the track before the current one again, right away
+#[poise::command(prefix_command, guild_only, aliases("back", "prev"))]
+pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let Some(call) = songbird.get(ctx.guild_id().unwrap()) else {
+        ctx.say("Not in a channel").await?;
+
+        return Ok(());
+    };
+
+    if let Err(reason) = play_previous(&PlayContext::new(ctx, call).await).await {
+        ctx.say(reason).await?;
+    }
+
+    Ok(())
+}
+
+// also used by the player buttons, the error is the reason shown to the user
+pub(super) async fn play_previous(ctx: &PlayContext) -> Result<(), &'static str> {
+    let current = ctx.call.lock().await.queue().current();
+
+    let previous = ctx
+        .guild
+        .history
+        .lock()
+        .unwrap()
+        .previous(current.as_ref())
+        .cloned();
+    let Some(previous) = previous else {
+        return Err("No previous track");
+    };
+
+    // the old input was consumed, so the track is looked up again
+    let source = ctx.source_factory().create(&previous.metadata.url).await;
+    let track = match source {
+        Ok(mut source) => source.next().await,
+        Err(_err) => None,
+    };
+    let Some(Ok(track)) = track else {
+        return Err("Error while loading the previous track");
+    };
+    ctx.guild.history.lock().unwrap().step_back(&previous);
+
+    let handle = enqueue(ctx, track).await;
+
+    // moved right after the current track, which is then skipped
+    if current.is_some() {
+        let queue = ctx.call.lock().await.queue().clone();
+        queue.modify_queue(|queue| {
+            if let Some(index) = queue.iter().position(|track| track.uuid() == handle.uuid()) {
+                let track = queue.remove(index).unwrap();
+                queue.insert(1, track);
+            }
+        });
+        let _ = queue.skip();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use super::*;
+use crate::commands::play::{
+    connect, format_duration, get_http_client, play_source, source_factory,
+};
+use crate::db::SavedTrack;
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use serenity::CreateAttachment;
+use sources::{JsonDuration, Metadata, SavedPlaylistSource};
+
+// tracks shown by the queue command, the export has all of them
+const MAX_LISTED: usize = 10;
+
+// the json form of an export, read back by queue import
+#[derive(Serialize, Deserialize)]
+struct ExportedQueue {
+    tracks: Vec<ExportedTrack>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedTrack {
+    title: String,
+    url: String,
+    #[serde(default)]
+    duration: JsonDuration,
+}
+
+enum ExportFormat {
+    Json,
+    M3u,
+    Text,
+}
+
+impl ExportFormat {
+    fn parse(name: &str) -> Option<ExportFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "m3u" | "m3u8" => Some(ExportFormat::M3u),
+            "text" | "txt" => Some(ExportFormat::Text),
+            _ => None,
+        }
+    }
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "queue.json",
+            ExportFormat::M3u => "queue.m3u",
+            ExportFormat::Text => "queue.txt",
+        }
+    }
+
+    fn write(&self, tracks: &[Metadata]) -> Result<String, Error> {
+        let text = match self {
+            ExportFormat::Json => {
+                let queue = ExportedQueue {
+                    tracks: tracks
+                        .iter()
+                        .map(|metadata| ExportedTrack {
+                            title: metadata.title.clone(),
+                            url: metadata.url.clone(),
+                            duration: metadata.duration.into(),
+                        })
+                        .collect(),
+                };
+
+                serde_json::to_string_pretty(&queue)?
+            }
+            // -1 is the length of streams in extended m3u
+            ExportFormat::M3u => tracks
+                .iter()
+                .fold("#EXTM3U\n".to_string(), |text, metadata| {
+                    let secs = metadata
+                        .duration
+                        .map_or(-1, |duration| duration.as_secs() as i64);
+
+                    text + &format!("#EXTINF:{secs},{}\n{}\n", metadata.title, metadata.url)
+                }),
+            ExportFormat::Text => tracks
+                .iter()
+                .enumerate()
+                .map(|(i, metadata)| {
+                    format!("{}. {} - {}\n", i + 1, describe(metadata), metadata.url)
+                })
+                .collect(),
+        };
+
+        Ok(text)
+    }
+}
+
+// lists the current queue, the first track is the one playing
+#[poise::command(
+    prefix_command,
+    guild_only,
+    aliases("q"),
+    subcommands("export", "import")
+)]
+pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    let tracks = queued_tracks(ctx).await;
+
+    if tracks.is_empty() {
+        ctx.say("The queue is empty").await?;
+
+        return Ok(());
+    }
+
+    let mut lines = tracks
+        .iter()
+        .take(MAX_LISTED)
+        .enumerate()
+        .map(|(i, metadata)| format!("{}. {}", i + 1, describe(metadata)))
+        .collect::<Vec<_>>();
+
+    if tracks.len() > MAX_LISTED {
+        lines.push(format!("and **{}** more", tracks.len() - MAX_LISTED));
+    }
+
+    ctx.say(lines.join("\n")).await?;
+
+    Ok(())
+}
+
+// sends the queue as a json, m3u or text file, json by default
+#[poise::command(prefix_command, guild_only)]
+pub async fn export(ctx: Context<'_>, format: Option<String>) -> Result<(), Error> {
+    let format = match format.as_deref().map(ExportFormat::parse) {
+        None => ExportFormat::Json,
+        Some(Some(format)) => format,
+        Some(None) => {
+            ctx.say("Formats are json, m3u and text").await?;
+
+            return Ok(());
+        }
+    };
+
+    let tracks = queued_tracks(ctx).await;
+
+    if tracks.is_empty() {
+        ctx.say("Nothing in the queue to export").await?;
+
+        return Ok(());
+    }
+
+    let attachment = CreateAttachment::bytes(format.write(&tracks)?, format.file_name());
+    let reply = CreateReply::default()
+        .content(format!("Exported **{}** songs", tracks.len()))
+        .attachment(attachment);
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+// plays a queue exported as json, attached to the message or linked
+#[poise::command(prefix_command, guild_only)]
+pub async fn import(ctx: Context<'_>, url: Option<String>) -> Result<(), Error> {
+    let Some(url) = url.or_else(|| json_attachment(ctx)) else {
+        ctx.say("Attach or link a queue exported as json").await?;
+
+        return Ok(());
+    };
+
+    let queue = match download_queue(ctx, &url).await {
+        Some(queue) if !queue.tracks.is_empty() => queue,
+        Some(_) => {
+            ctx.say("The exported queue is empty").await?;
+
+            return Ok(());
+        }
+        None => {
+            ctx.say("Error while reading the exported queue").await?;
+
+            return Ok(());
+        }
+    };
+
+    let Some(call) = connect(ctx).await? else {
+        return Ok(());
+    };
+
+    ctx.say(format!("Importing **{}** songs", queue.tracks.len()))
+        .await?;
+
+    // resolved again from their urls, like saved playlists
+    let tracks = queue
+        .tracks
+        .into_iter()
+        .map(|track| SavedTrack {
+            title: track.title,
+            url: track.url,
+        })
+        .collect();
+    let factory = source_factory(ctx).await;
+    let source = SavedPlaylistSource::new(tracks, factory.clone());
+
+    play_source(
+        ctx,
+        &call,
+        factory.measure("imported queue", Box::new(source)),
+    )
+    .await
+}
+
+async fn queued_tracks(ctx: Context<'_>) -> Vec<Metadata> {
+    let songbird = get_songbird(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let queue = match songbird.get(ctx.guild_id().unwrap()) {
+        Some(call) => call.lock().await.queue().current_queue(),
+        None => Vec::new(),
+    };
+
+    let mut tracks = Vec::new();
+    for track in queue {
+        if let Some(metadata) = track.typemap().read().await.get::<Metadata>() {
+            tracks.push(metadata.clone());
+        }
+    }
+
+    tracks
+}
+
+fn json_attachment(ctx: Context<'_>) -> Option<String> {
+    let Context::Prefix(ctx) = ctx else {
+        return None;
+    };
+
+    ctx.msg
+        .attachments
+        .iter()
+        .find(|attachment| attachment.filename.to_ascii_lowercase().ends_with(".json"))
+        .map(|attachment| attachment.url.clone())
+}
+
+async fn download_queue(ctx: Context<'_>, url: &str) -> Option<ExportedQueue> {
+    let text = get_http_client(ctx)
+        .await
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+
+    serde_json::from_str(&text).ok()
+}
+
+// "title (3:45)"
+fn describe(metadata: &Metadata) -> String {
+    let duration = match metadata.duration {
+        Some(duration) => format_duration(duration),
+        None => "live".to_string(),
+    };
+
+    format!("{} ({duration})", metadata.title)
+}
@@ -0,0 +1,336 @@
+use super::*;
+use crate::audio::{track_position, MAX_VOLUME};
+use crate::commands::play::{enqueue, PlayContext};
+use crate::player::PlayerEvent;
+use poise::serenity_prelude as serenity;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use serenity::{ChannelId, GuildId, User};
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
+use songbird::Call;
+use sources::{JsonDuration, Metadata};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+// why an action of the player couldn't be done, shown to the user as it is
+#[derive(Debug)]
+pub enum PlayerError {
+    NotConnected,
+    NothingPlaying,
+    NoSuchTrack,
+    Live,
+    BeyondEnd,
+    NoSongsFound,
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            PlayerError::NotConnected => "Not in a channel",
+            PlayerError::NothingPlaying => "Nothing playing",
+            PlayerError::NoSuchTrack => "No such track in the queue",
+            PlayerError::Live => "Can't seek in a live stream",
+            PlayerError::BeyondEnd => "Skipped beyond the end of song",
+            PlayerError::NoSongsFound => "No songs found",
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl std::error::Error for PlayerError {}
+
+// the queue of a guild with the state of its player
+#[derive(Serialize)]
+pub struct QueueState {
+    // the first track is the one playing
+    pub tracks: Vec<QueuedTrack>,
+    // of the playing track, in seconds
+    pub position: Option<f64>,
+    pub paused: bool,
+    pub looping: bool,
+    pub volume: f32,
+}
+
+#[derive(Serialize)]
+pub struct QueuedTrack {
+    pub title: String,
+    pub url: String,
+    pub duration: JsonDuration,
+    pub source: String,
+}
+
+impl From<&Metadata> for QueuedTrack {
+    fn from(metadata: &Metadata) -> Self {
+        QueuedTrack {
+            title: metadata.title.clone(),
+            url: metadata.url.clone(),
+            duration: metadata.duration.into(),
+            source: metadata.kind.to_string(),
+        }
+    }
+}
+
+pub async fn call(
+    ctx: &serenity::Context,
+    guild: GuildId,
+) -> Result<Arc<Mutex<Call>>, PlayerError> {
+    let songbird = get_songbird(ctx).await.expect("Songbird not registered");
+
+    songbird.get(guild).ok_or(PlayerError::NotConnected)
+}
+
+async fn current_track(
+    ctx: &serenity::Context,
+    guild: GuildId,
+) -> Result<TrackHandle, PlayerError> {
+    let call = call(ctx, guild).await?;
+    let current = call.lock().await.queue().current();
+
+    current.ok_or(PlayerError::NothingPlaying)
+}
+
+pub async fn queue_state(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+) -> Result<QueueState, PlayerError> {
+    let call = call(ctx, guild).await?;
+    let queue = call.lock().await.queue().current_queue();
+
+    let mut tracks = Vec::new();
+    for track in &queue {
+        if let Some(metadata) = track.typemap().read().await.get::<Metadata>() {
+            tracks.push(QueuedTrack::from(metadata));
+        }
+    }
+
+    let mut state = QueueState {
+        tracks,
+        position: None,
+        paused: false,
+        looping: false,
+        volume: data.guild(guild).filters.settings().volume,
+    };
+
+    if let Some(current) = queue.first() {
+        if let Ok(info) = current.get_info().await {
+            state.paused = info.playing == PlayMode::Pause;
+            state.looping = info.loops != LoopState::Finite(0);
+        }
+        state.position = track_position(current)
+            .await
+            .ok()
+            .map(|position| position.as_secs_f64());
+    }
+
+    Ok(state)
+}
+
+// looks the query up and queues the first track found, the rest of a playlist is queued in
+// the background. The guild needs a voice connection
+pub async fn enqueue_query(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+    channel: ChannelId,
+    author: User,
+    query: &str,
+) -> Result<Metadata, PlayerError> {
+    let call = call(ctx, guild).await?;
+    let play_ctx = PlayContext::from_parts(ctx, data, guild, channel, author, call).await;
+
+    let Ok(mut source) = play_ctx.source_factory().create(query).await else {
+        return Err(PlayerError::NoSongsFound);
+    };
+
+    let first = loop {
+        match source.next().await {
+            Some(Ok(track)) => break track,
+            Some(Err(err)) if err.ends_source() => return Err(PlayerError::NoSongsFound),
+            Some(Err(_)) => continue,
+            None => return Err(PlayerError::NoSongsFound),
+        }
+    };
+    let metadata = first.metadata.clone();
+    enqueue(&play_ctx, first).await;
+
+    tokio::spawn(async move {
+        while let Some(track) = source.next().await {
+            match track {
+                Ok(track) => {
+                    enqueue(&play_ctx, track).await;
+                }
+                Err(err) if err.ends_source() => break,
+                Err(_) => continue,
+            }
+        }
+    });
+
+    Ok(metadata)
+}
+
+pub async fn skip(ctx: &serenity::Context, data: &Data, guild: GuildId) -> Result<(), PlayerError> {
+    let call = call(ctx, guild).await?;
+    let _ = call.lock().await.queue().skip();
+
+    queue_changed(data, guild);
+
+    Ok(())
+}
+
+pub async fn stop(ctx: &serenity::Context, data: &Data, guild: GuildId) -> Result<(), PlayerError> {
+    let call = call(ctx, guild).await?;
+    data.guild(guild).stopped.store(true, Ordering::Relaxed);
+    call.lock().await.queue().stop();
+
+    queue_changed(data, guild);
+
+    Ok(())
+}
+
+// the current track keeps playing
+pub async fn shuffle(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+) -> Result<(), PlayerError> {
+    let call = call(ctx, guild).await?;
+    call.lock().await.queue().modify_queue(|queue| {
+        if queue.len() > 2 {
+            queue.make_contiguous()[1..].shuffle(&mut rand::thread_rng());
+        }
+    });
+
+    queue_changed(data, guild);
+
+    Ok(())
+}
+
+// positions in the queue, 0 is the playing track and can't be moved
+pub async fn move_track(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+    from: usize,
+    to: usize,
+) -> Result<(), PlayerError> {
+    let call = call(ctx, guild).await?;
+    let moved = call.lock().await.queue().modify_queue(|queue| {
+        let len = queue.len();
+        if from == 0 || to == 0 || from >= len || to >= len {
+            return false;
+        }
+
+        let track = queue.remove(from).unwrap();
+        queue.insert(to, track);
+
+        true
+    });
+
+    if !moved {
+        return Err(PlayerError::NoSuchTrack);
+    }
+
+    queue_changed(data, guild);
+
+    Ok(())
+}
+
+pub async fn set_paused(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+    paused: bool,
+) -> Result<(), PlayerError> {
+    let track = current_track(ctx, guild).await?;
+
+    let guild = guild.to_string();
+    if paused {
+        let _ = track.pause();
+        data.events.send(PlayerEvent::Paused { guild });
+    } else {
+        let _ = track.play();
+        data.events.send(PlayerEvent::Resumed { guild });
+    }
+
+    Ok(())
+}
+
+pub async fn set_looping(
+    ctx: &serenity::Context,
+    guild: GuildId,
+    looping: bool,
+) -> Result<(), PlayerError> {
+    let track = current_track(ctx, guild).await?;
+
+    let _ = if looping {
+        track.enable_loop()
+    } else {
+        track.disable_loop()
+    };
+
+    Ok(())
+}
+
+// to an absolute position of the current track, returns where it got to
+pub async fn seek(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: GuildId,
+    position: Duration,
+) -> Result<Duration, PlayerError> {
+    let track = current_track(ctx, guild).await?;
+
+    let is_live = track
+        .typemap()
+        .read()
+        .await
+        .get::<Metadata>()
+        .is_some_and(|metadata| metadata.is_live);
+    if is_live {
+        return Err(PlayerError::Live);
+    }
+
+    let Ok(position) = track.seek_async(position).await else {
+        return Err(PlayerError::BeyondEnd);
+    };
+
+    data.events.send(PlayerEvent::Seeked {
+        guild: guild.to_string(),
+        position: position.as_secs_f64(),
+    });
+
+    Ok(position)
+}
+
+// position of the current track, for seeking relative to it
+pub async fn position(ctx: &serenity::Context, guild: GuildId) -> Result<Duration, PlayerError> {
+    let track = current_track(ctx, guild).await?;
+
+    track_position(&track)
+        .await
+        .map_err(|_err| PlayerError::NothingPlaying)
+}
+
+// clamped to what the filters allow, returns the volume that was set
+pub fn set_volume(data: &Data, guild: GuildId, volume: f32) -> f32 {
+    let volume = volume.clamp(0.0, MAX_VOLUME);
+    data.guild(guild).filters.set_volume(volume);
+
+    data.events.send(PlayerEvent::VolumeChanged {
+        guild: guild.to_string(),
+        volume,
+    });
+
+    volume
+}
+
+fn queue_changed(data: &Data, guild: GuildId) {
+    data.events.send(PlayerEvent::QueueChanged {
+        guild: guild.to_string(),
+    });
+}
@@ -0,0 +1,33 @@
+use super::*;
+use sources::SearchSource;
+
+// where songs are searched when the query has no prefix, without arguments shows it
+#[poise::command(prefix_command, guild_only, aliases("searchsource"))]
+pub async fn source(ctx: Context<'_>, name: Option<String>) -> Result<(), Error> {
+    let guild = ctx.data().guild(ctx.guild_id().unwrap());
+
+    let Some(name) = name else {
+        let source = *guild.default_source.read().unwrap();
+        ctx.say(format!("Songs are searched with **`{source}:`**"))
+            .await?;
+
+        return Ok(());
+    };
+
+    let Ok(source) = name.parse::<SearchSource>() else {
+        let prefixes = SearchSource::ALL
+            .iter()
+            .map(|source| format!("`{source}`"))
+            .collect::<Vec<_>>();
+        ctx.say(format!("Source must be one of {}", prefixes.join(", ")))
+            .await?;
+
+        return Ok(());
+    };
+
+    *guild.default_source.write().unwrap() = source;
+    ctx.say(format!("Songs are now searched with **`{source}:`**"))
+        .await?;
+
+    Ok(())
+}
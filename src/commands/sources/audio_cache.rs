@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use symphonia::core::io::MediaSource;
+use tokio::runtime::Handle;
+use tracing::{debug, warn};
+
+// files still being downloaded, removed when the bot starts
+const PART_EXTENSION: &str = "part";
+
+// audio of YouTube videos kept on disk so popular songs aren't streamed again. Files are
+// named by video id and format, the least recently played ones are removed once they take
+// more space than allowed
+#[derive(Clone)]
+pub struct AudioCache(Arc<Inner>);
+
+struct Inner {
+    dir: PathBuf,
+    max_size: u64,
+    files: Mutex<Files>,
+    // files are written from the mixer's thread and looked up from async ones, the disk is
+    // only touched on the runtime's blocking threads
+    runtime: Handle,
+}
+
+#[derive(Default)]
+struct Files {
+    entries: HashMap<String, CachedFile>,
+    size: u64,
+}
+
+struct CachedFile {
+    size: u64,
+    // the modification time on disk, so the order survives restarts
+    used: SystemTime,
+}
+
+pub struct AudioCacheStats {
+    pub files: usize,
+    pub size: u64,
+}
+
+impl AudioCache {
+    // files of earlier runs are kept, has to be called on the runtime
+    pub fn open(dir: PathBuf, max_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let mut files = Files::default();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+
+            // the directory may hold other files, only the cache's own are touched
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            if is_part_name(&name) {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if !is_cache_name(&name) {
+                continue;
+            }
+            files.size += metadata.len();
+            files.entries.insert(
+                name,
+                CachedFile {
+                    size: metadata.len(),
+                    used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                },
+            );
+        }
+
+        let cache = AudioCache(Arc::new(Inner {
+            dir,
+            max_size,
+            files: Mutex::new(files),
+            runtime: Handle::current(),
+        }));
+        cache.evict(None);
+
+        Ok(cache)
+    }
+
+    // path of the file if it's cached, it counts as played
+    pub fn get(&self, name: &str) -> Option<PathBuf> {
+        let mut files = self.0.files.lock().unwrap();
+        let file = files.entries.get_mut(name)?;
+
+        let now = SystemTime::now();
+        file.used = now;
+
+        let path = self.0.dir.join(name);
+        let touched = path.clone();
+        self.0.runtime.spawn_blocking(move || {
+            if let Ok(file) = File::options().write(true).open(&touched) {
+                let _ = file.set_modified(now);
+            }
+        });
+
+        Some(path)
+    }
+
+    // the stream is written to the cache as it is read, only whole files are kept
+    pub fn wrap(&self, name: String, stream: Box<dyn MediaSource>) -> Box<dyn MediaSource> {
+        // streams of the same file in other guilds write their own parts
+        let part = self.0.dir.join(format!(
+            "{name}.{:08x}.{PART_EXTENSION}",
+            rand::random::<u32>()
+        ));
+
+        match File::create(&part) {
+            Ok(file) => Box::new(CachingSource {
+                // asking the stream for it can wait on its downloading thread
+                byte_len: stream.byte_len(),
+                inner: stream,
+                file: Some(file),
+                part,
+                name,
+                written: 0,
+                position: 0,
+                cache: self.clone(),
+            }),
+            Err(err) => {
+                warn!(error = %err, "audio cache file not created");
+                stream
+            }
+        }
+    }
+
+    pub fn stats(&self) -> AudioCacheStats {
+        let files = self.0.files.lock().unwrap();
+
+        AudioCacheStats {
+            files: files.entries.len(),
+            size: files.size,
+        }
+    }
+
+    fn insert(&self, name: String, part: &Path, size: u64) {
+        if let Err(err) = fs::rename(part, self.0.dir.join(&name)) {
+            warn!(error = %err, "audio cache file not saved");
+            let _ = fs::remove_file(part);
+            return;
+        }
+
+        let mut files = self.0.files.lock().unwrap();
+        let file = CachedFile {
+            size,
+            used: SystemTime::now(),
+        };
+        if let Some(replaced) = files.entries.insert(name.clone(), file) {
+            files.size -= replaced.size;
+        }
+        files.size += size;
+        drop(files);
+
+        debug!(name, size, "audio cached");
+        self.evict(Some(&name));
+    }
+
+    // removes the least recently played files until they fit, the one just added goes last
+    fn evict(&self, added: Option<&str>) {
+        let mut files = self.0.files.lock().unwrap();
+
+        while files.size > self.0.max_size {
+            let oldest = files
+                .entries
+                .iter()
+                .min_by_key(|(name, file)| (Some(name.as_str()) == added, file.used))
+                .map(|(name, _)| name.clone());
+            let Some(name) = oldest else {
+                break;
+            };
+
+            let file = files.entries.remove(&name).unwrap();
+            files.size -= file.size;
+
+            if let Err(err) = fs::remove_file(self.0.dir.join(&name)) {
+                warn!(name, error = %err, "audio cache file not removed");
+            }
+        }
+    }
+}
+
+// passes a stream through while copying it to a file. Only what is read right after the
+// copied part is written, so the copy waits while the track is seeked ahead and continues
+// once playback gets back to where it stopped
+struct CachingSource {
+    inner: Box<dyn MediaSource>,
+    byte_len: Option<u64>,
+    // None once the copy is finished
+    file: Option<File>,
+    part: PathBuf,
+    name: String,
+    written: u64,
+    position: u64,
+    cache: AudioCache,
+}
+
+impl CachingSource {
+    fn finish(&mut self) {
+        let Some(mut file) = self.file.take() else {
+            return;
+        };
+
+        let (cache, name, part, size) = (
+            self.cache.clone(),
+            self.name.clone(),
+            self.part.clone(),
+            self.written,
+        );
+        self.cache.0.runtime.spawn_blocking(move || {
+            if file.flush().is_ok() {
+                cache.insert(name, &part, size);
+            } else {
+                let _ = fs::remove_file(&part);
+            }
+        });
+    }
+
+    fn abandon(&mut self) {
+        if self.file.take().is_some() {
+            let part = self.part.clone();
+            self.cache.0.runtime.spawn_blocking(move || {
+                let _ = fs::remove_file(part);
+            });
+        }
+    }
+}
+
+impl Read for CachingSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+
+        if self.position == self.written {
+            if let Some(file) = &mut self.file {
+                if len == 0 && !buf.is_empty() {
+                    // a stream of known length that ended early was cut off
+                    if self.byte_len.is_none() {
+                        self.finish();
+                    } else {
+                        self.abandon();
+                    }
+                } else if file.write_all(&buf[..len]).is_ok() {
+                    self.written += len as u64;
+                    if self.byte_len == Some(self.written) {
+                        self.finish();
+                    }
+                } else {
+                    self.abandon();
+                }
+            }
+        }
+        self.position += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl Seek for CachingSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for CachingSource {
+    fn is_seekable(&self) -> bool {
+        self.inner.is_seekable()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.byte_len
+    }
+}
+
+// tracks that stop early leave their part behind
+impl Drop for CachingSource {
+    fn drop(&mut self) {
+        self.abandon();
+    }
+}
+
+// like "dQw4w9WgXcQ-251.webm", the names youtube.rs gives the files
+fn is_cache_name(name: &str) -> bool {
+    let Some((stem, extension)) = name.rsplit_once('.') else {
+        return false;
+    };
+    let Some((video_id, itag)) = stem.rsplit_once('-') else {
+        return false;
+    };
+
+    video_id.len() == 11
+        && video_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !itag.is_empty()
+        && itag.chars().all(|c| c.is_ascii_digit())
+        && !extension.is_empty()
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// "<cache name>.<8 hex digits>.part"
+fn is_part_name(name: &str) -> bool {
+    let Some(rest) = name
+        .strip_suffix(PART_EXTENSION)
+        .and_then(|rest| rest.strip_suffix('.'))
+    else {
+        return false;
+    };
+    let Some((name, suffix)) = rest.rsplit_once('.') else {
+        return false;
+    };
+
+    suffix.len() == 8 && suffix.chars().all(|c| c.is_ascii_hexdigit()) && is_cache_name(name)
+}
@@ -0,0 +1,96 @@
+use crate::lru::LruMap;
+use std::any::Any;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Value = Arc<dyn Any + Send + Sync>;
+
+// what sources resolved from a link or a query, shared by every guild so the same song
+// isn't looked up again. Entries expire before the stream urls in them would
+#[derive(Clone)]
+pub struct ResolverCache(Arc<Inner>);
+
+struct Inner {
+    entries: Mutex<LruMap<String, Entry>>,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Entry {
+    value: Value,
+    inserted: Instant,
+}
+
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl ResolverCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        ResolverCache(Arc::new(Inner {
+            entries: Mutex::new(LruMap::new(capacity)),
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }))
+    }
+
+    // keys start with the source, like "yt:<video id>"
+    pub fn get<T: Any + Send + Sync>(&self, key: &str) -> Option<Arc<T>> {
+        let mut entries = self.0.entries.lock().unwrap();
+
+        let value = match entries.get_mut(key) {
+            Some(entry) if entry.inserted.elapsed() < self.0.ttl => {
+                entry.value.clone().downcast().ok()
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if value.is_some() {
+            &self.0.hits
+        } else {
+            &self.0.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        value
+    }
+
+    pub fn insert<T: Any + Send + Sync>(&self, key: String, value: Arc<T>) {
+        let mut entries = self.0.entries.lock().unwrap();
+        entries.retain(|entry| entry.inserted.elapsed() < self.0.ttl);
+
+        entries.insert(
+            key,
+            Entry {
+                value,
+                inserted: Instant::now(),
+            },
+        );
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.0.hits.load(Ordering::Relaxed),
+            misses: self.0.misses.load(Ordering::Relaxed),
+            entries: self.0.entries.lock().unwrap().len(),
+        }
+    }
+}
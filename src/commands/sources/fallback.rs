@@ -0,0 +1,61 @@
+use super::audio_cache::AudioCache;
+use serenity::async_trait;
+use songbird::input::{AudioStream, AudioStreamError, AuxMetadata, Compose, HttpRequest};
+use symphonia::core::io::MediaSource;
+use tracing::debug;
+
+// formats of the same song tried in order until one opens, YouTube sometimes refuses the
+// stream of one format while the others still play
+pub struct FallbackRequest {
+    // with the names of their files in the audio cache
+    requests: Vec<(HttpRequest, String)>,
+    cache: Option<AudioCache>,
+}
+
+impl FallbackRequest {
+    pub fn new(requests: Vec<(HttpRequest, String)>, cache: Option<AudioCache>) -> Self {
+        FallbackRequest { requests, cache }
+    }
+}
+
+#[async_trait]
+impl Compose for FallbackRequest {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+
+    async fn create_async(
+        &mut self,
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let mut last_err = AudioStreamError::Unsupported;
+
+        for (i, (request, name)) in self.requests.iter_mut().enumerate() {
+            match request.create_async().await {
+                Ok(mut stream) => {
+                    if let Some(cache) = &self.cache {
+                        stream.input = cache.wrap(name.clone(), stream.input);
+                    }
+
+                    // formats that failed aren't tried again when the track is recreated
+                    self.requests.drain(..i);
+
+                    return Ok(stream);
+                }
+                Err(err) => {
+                    debug!(format = i, error = %err, "format not opened");
+                    last_err = err;
+                }
+            }
+        }
+
+        Err(last_err)
+    }
+
+    fn should_create_async(&self) -> bool {
+        true
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+}
@@ -0,0 +1,388 @@
+use reqwest::{Client as HttpClient, Url};
+use serenity::async_trait;
+use songbird::input::{
+    AsyncAdapterStream, AsyncMediaSource, AudioStream, AudioStreamError, AuxMetadata, Compose,
+};
+use std::io::{Error as IoError, ErrorKind, Result as IoResult, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use symphonia::core::{io::MediaSource, probe::Hint};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tokio::sync::mpsc;
+
+const TS_PACKET_LEN: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+// stream type of AAC in ADTS frames in the program map table
+const STREAM_TYPE_ADTS: u8 = 0x0f;
+// how far behind the live edge playback starts, as recommended by the HLS spec
+const LIVE_EDGE_SEGMENTS: usize = 3;
+// segments buffered ahead of the decoder
+const SEGMENT_BUFFER: usize = 4;
+const ADAPTER_BUFFER_LEN: usize = 256 * 1024;
+
+// stream served as an HLS media playlist, live ones are reloaded for new segments. The audio
+// of MPEG-TS segments is extracted, other segments are already audio and played as they are
+pub struct HlsRequest {
+    client: HttpClient,
+    playlist_url: String,
+    // format of the audio once it's out of the segments
+    extension: &'static str,
+}
+
+impl HlsRequest {
+    pub fn new(client: HttpClient, playlist_url: String, extension: &'static str) -> Self {
+        HlsRequest {
+            client,
+            playlist_url,
+            extension,
+        }
+    }
+}
+
+#[async_trait]
+impl Compose for HlsRequest {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+
+    async fn create_async(
+        &mut self,
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let (sender, receiver) = mpsc::channel(SEGMENT_BUFFER);
+        let mut playlist = LivePlaylist::new(self.client.clone(), self.playlist_url.clone());
+
+        // fail early when the playlist can't be loaded at all
+        let segments = playlist
+            .refresh()
+            .await
+            .map_err(|err| AudioStreamError::Fail(err.into()))?;
+        tokio::spawn(playlist.stream(segments, sender));
+
+        let reader = SegmentReader {
+            receiver,
+            demuxer: TsDemuxer::default(),
+            pending: Vec::new(),
+            pending_pos: 0,
+        };
+
+        let mut hint = Hint::new();
+        hint.with_extension(self.extension);
+
+        Ok(AudioStream {
+            input: Box::new(AsyncAdapterStream::new(
+                Box::new(reader),
+                ADAPTER_BUFFER_LEN,
+            )),
+            hint: Some(hint),
+        })
+    }
+
+    fn should_create_async(&self) -> bool {
+        true
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+}
+
+// media playlist that is reloaded to find segments added since the last load
+struct LivePlaylist {
+    client: HttpClient,
+    url: String,
+    // media sequence number of the next segment to download
+    next_sequence: Option<u64>,
+    target_duration: Duration,
+    ended: bool,
+}
+
+impl LivePlaylist {
+    fn new(client: HttpClient, url: String) -> Self {
+        LivePlaylist {
+            client,
+            url,
+            next_sequence: None,
+            target_duration: Duration::from_secs(2),
+            ended: false,
+        }
+    }
+
+    // urls of the segments that weren't returned yet
+    async fn refresh(&mut self) -> reqwest::Result<Vec<String>> {
+        let text = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut sequence = 0;
+        let mut segments = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                sequence = value.parse().unwrap_or(0);
+            } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                if let Ok(secs) = value.parse() {
+                    self.target_duration = Duration::from_secs(secs);
+                }
+            } else if line == "#EXT-X-ENDLIST" {
+                self.ended = true;
+            } else if !line.is_empty() && !line.starts_with('#') {
+                segments.push((sequence, self.resolve(line)));
+                sequence += 1;
+            }
+        }
+
+        let first_new = match self.next_sequence {
+            Some(next) => next,
+            // finished streams are played from the start
+            None if self.ended => segments.first().map_or(0, |(sequence, _)| *sequence),
+            None => {
+                let start = segments.len().saturating_sub(LIVE_EDGE_SEGMENTS);
+                segments.get(start).map_or(0, |(sequence, _)| *sequence)
+            }
+        };
+        self.next_sequence = Some(first_new.max(sequence));
+
+        Ok(segments
+            .into_iter()
+            .filter(|(sequence, _)| *sequence >= first_new)
+            .map(|(_, url)| url)
+            .collect())
+    }
+
+    // segment uris can be relative to the playlist, or to its host when they start with "/"
+    fn resolve(&self, uri: &str) -> String {
+        Url::parse(&self.url)
+            .and_then(|base| base.join(uri))
+            .map(String::from)
+            .unwrap_or_else(|_err| uri.to_string())
+    }
+
+    // downloads segments until the stream ends or the track is dropped
+    async fn stream(mut self, mut segments: Vec<String>, sender: mpsc::Sender<Vec<u8>>) {
+        loop {
+            for url in segments {
+                let segment = match self.download(&url).await {
+                    Ok(segment) => segment,
+                    // segment expired from the server, the next one is tried
+                    Err(_err) => continue,
+                };
+
+                if sender.send(segment).await.is_err() {
+                    return;
+                }
+            }
+
+            if self.ended {
+                return;
+            }
+
+            // new segments show up about once per target duration
+            tokio::time::sleep(self.target_duration).await;
+
+            // a failed reload is retried after the next wait
+            segments = self.refresh().await.unwrap_or_default();
+        }
+    }
+
+    async fn download(&self, url: &str) -> reqwest::Result<Vec<u8>> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+// reads downloaded segments as one stream of their audio
+struct SegmentReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    demuxer: TsDemuxer,
+    pending: Vec<u8>,
+    pending_pos: usize,
+}
+
+impl AsyncRead for SegmentReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        while self.pending_pos == self.pending.len() {
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some(segment)) => {
+                    let this = &mut *self;
+                    this.pending.clear();
+                    this.pending_pos = 0;
+                    this.demuxer.demux(&segment, &mut this.pending);
+                }
+                // the stream has ended
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let len = buf.remaining().min(self.pending.len() - self.pending_pos);
+        let start = self.pending_pos;
+        buf.put_slice(&self.pending[start..start + len]);
+        self.pending_pos += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+// live streams can't be seeked
+impl AsyncSeek for SegmentReader {
+    fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> IoResult<()> {
+        Err(IoError::new(
+            ErrorKind::Unsupported,
+            "live streams can't seek",
+        ))
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<u64>> {
+        Poll::Ready(Err(IoError::new(
+            ErrorKind::Unsupported,
+            "live streams can't seek",
+        )))
+    }
+}
+
+#[async_trait]
+impl AsyncMediaSource for SegmentReader {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+// extracts the payload of the first AAC stream from MPEG-TS packets, the state is kept
+// between segments since every segment continues the same transport stream
+#[derive(Default)]
+struct TsDemuxer {
+    pmt_pid: Option<u16>,
+    audio_pid: Option<u16>,
+    // payload of a PES packet is only written once its header was skipped
+    in_pes: bool,
+}
+
+impl TsDemuxer {
+    fn demux(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        if data.first() != Some(&TS_SYNC_BYTE) {
+            output.extend_from_slice(data);
+
+            return;
+        }
+
+        for packet in data.chunks_exact(TS_PACKET_LEN) {
+            if packet[0] != TS_SYNC_BYTE {
+                continue;
+            }
+
+            let unit_start = packet[1] & 0x40 != 0;
+            let pid = u16::from_be_bytes([packet[1] & 0x1f, packet[2]]);
+            let adaptation = (packet[3] >> 4) & 0x3;
+
+            // adaptation field comes before the payload
+            let mut start = 4;
+            if adaptation & 0x2 != 0 {
+                start += 1 + packet[4] as usize;
+            }
+            if adaptation & 0x1 == 0 || start >= TS_PACKET_LEN {
+                continue;
+            }
+            let payload = &packet[start..];
+
+            if pid == 0 {
+                self.parse_pat(unit_start, payload);
+            } else if Some(pid) == self.pmt_pid {
+                self.parse_pmt(unit_start, payload);
+            } else if Some(pid) == self.audio_pid {
+                self.parse_pes(unit_start, payload, output);
+            }
+        }
+    }
+
+    fn parse_pat(&mut self, unit_start: bool, payload: &[u8]) {
+        let Some(section) = psi_section(unit_start, payload) else {
+            return;
+        };
+
+        // program entries of 4 bytes follow the 8 byte header, the crc is at the end
+        let entries = section
+            .get(8..section.len().saturating_sub(4))
+            .unwrap_or(&[]);
+        for entry in entries.chunks_exact(4) {
+            let program = u16::from_be_bytes([entry[0], entry[1]]);
+
+            // program 0 points to the network information table
+            if program != 0 {
+                self.pmt_pid = Some(u16::from_be_bytes([entry[2] & 0x1f, entry[3]]));
+                return;
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, unit_start: bool, payload: &[u8]) {
+        let Some(section) = psi_section(unit_start, payload) else {
+            return;
+        };
+        if section.len() < 12 {
+            return;
+        }
+
+        let info_len = u16::from_be_bytes([section[10] & 0x0f, section[11]]) as usize;
+        let end = section.len().saturating_sub(4);
+        let mut pos = 12 + info_len;
+
+        while pos + 5 <= end {
+            let stream_type = section[pos];
+            let pid = u16::from_be_bytes([section[pos + 1] & 0x1f, section[pos + 2]]);
+            let es_info_len = u16::from_be_bytes([section[pos + 3] & 0x0f, section[pos + 4]]);
+
+            if stream_type == STREAM_TYPE_ADTS {
+                self.audio_pid = Some(pid);
+                return;
+            }
+
+            pos += 5 + es_info_len as usize;
+        }
+    }
+
+    fn parse_pes(&mut self, unit_start: bool, payload: &[u8], output: &mut Vec<u8>) {
+        if !unit_start {
+            if self.in_pes {
+                output.extend_from_slice(payload);
+            }
+            return;
+        }
+
+        // start code, stream id, length, two flag bytes and the header length
+        if payload.len() < 9 || payload[..3] != [0, 0, 1] {
+            self.in_pes = false;
+            return;
+        }
+
+        let header_len = 9 + payload[8] as usize;
+        self.in_pes = true;
+        output.extend_from_slice(payload.get(header_len..).unwrap_or(&[]));
+    }
+}
+
+// table section of a PSI packet, only sections starting in the packet are handled
+fn psi_section(unit_start: bool, payload: &[u8]) -> Option<&[u8]> {
+    if !unit_start {
+        return None;
+    }
+
+    let section = payload.get(1 + *payload.first()? as usize..)?;
+    let len = 3 + (u16::from_be_bytes([*section.get(1)? & 0x0f, *section.get(2)?]) as usize);
+
+    section.get(..len)
+}
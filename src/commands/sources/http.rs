@@ -0,0 +1,59 @@
+use super::hls::HlsRequest;
+use super::{Metadata, Result, SourceError as Error, SourceKind, TrackInfo, TrackSource};
+use reqwest::Client as HttpClient;
+use serenity::async_trait;
+use songbird::input::{HttpRequest, Input};
+
+// audio file behind a direct link
+pub struct HttpSource {
+    url: Option<String>,
+    client: HttpClient,
+    // the link is an HLS media playlist
+    hls: bool,
+}
+
+impl HttpSource {
+    pub fn new(url: &str, client: HttpClient) -> Result<Self> {
+        if !url.starts_with("http") {
+            return Err(Error::Other);
+        }
+
+        Ok(HttpSource {
+            url: Some(url.to_string()),
+            client,
+            hls: false,
+        })
+    }
+
+    pub fn hls(url: &str, client: HttpClient) -> Result<Self> {
+        Ok(HttpSource {
+            hls: true,
+            ..HttpSource::new(url, client)?
+        })
+    }
+}
+
+#[async_trait]
+impl TrackSource for HttpSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        let url = self.url.take()?;
+
+        // name of the file, without the query
+        let title = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').find(|part| !part.is_empty()))
+            .unwrap_or(&url)
+            .to_string();
+
+        let metadata = Metadata::new(title, url.clone(), String::new(), SourceKind::Http);
+        let input = if self.hls {
+            // segments of streams on the web are usually AAC
+            Input::Lazy(Box::new(HlsRequest::new(self.client.clone(), url, "aac")))
+        } else {
+            HttpRequest::new(self.client.clone(), url).into()
+        };
+
+        Some(Ok(TrackInfo::new(metadata, input)))
+    }
+}
@@ -0,0 +1,94 @@
+use super::{Metadata, Result, SourceError as Error, SourceKind, TrackInfo, TrackSource};
+use serenity::async_trait;
+use songbird::input::File;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "mkv"];
+
+// file from the music directory, by its path in it or a part of its name
+pub struct LocalSource {
+    // path relative to the music directory and the full path
+    file: Option<(String, PathBuf)>,
+}
+
+impl LocalSource {
+    // the search walks the directory, which isn't done on the runtime's threads
+    pub async fn new(query: &str, dir: &Path) -> Result<Self> {
+        let (query, dir) = (query.to_string(), dir.to_path_buf());
+
+        tokio::task::spawn_blocking(move || LocalSource::find(&query, &dir))
+            .await
+            .map_err(|_err| Error::Other)?
+    }
+
+    fn find(query: &str, dir: &Path) -> Result<Self> {
+        let dir = dir.canonicalize().map_err(|_err| Error::Other)?;
+
+        let path = match dir.join(query).canonicalize() {
+            Ok(path) if path.is_file() => path,
+            _ => find_file(&dir, &query.to_lowercase())
+                .and_then(|path| path.canonicalize().ok())
+                .ok_or(Error::Other)?,
+        };
+
+        // paths like "../" or symlinks can't leave the directory
+        let relative = path.strip_prefix(&dir).map_err(|_err| Error::Other)?;
+
+        Ok(LocalSource {
+            file: Some((relative.to_string_lossy().into_owned(), path)),
+        })
+    }
+}
+
+#[async_trait]
+impl TrackSource for LocalSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        let (relative, path) = self.file.take()?;
+
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| relative.clone());
+
+        let metadata = Metadata::new(
+            title,
+            format!("local:{relative}"),
+            String::new(),
+            SourceKind::Local,
+        );
+
+        Some(Ok(TrackInfo::new(metadata, File::new(path).into())))
+    }
+}
+
+// first audio file, in name order, whose name contains the query
+fn find_file(dir: &Path, query: &str) -> Option<PathBuf> {
+    let mut entries = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let found = entries.iter().find(|path| {
+        let is_audio = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        path.is_file() && is_audio && name.contains(query)
+    });
+
+    found.cloned().or_else(|| {
+        entries
+            .iter()
+            // symlinked directories may lead out of it or back up in a loop
+            .filter(|path| fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir()))
+            .find_map(|path| find_file(path, query))
+    })
+}
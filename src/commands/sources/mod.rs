@@ -1,28 +1,306 @@
+mod audio_cache;
+mod cache;
+mod fallback;
+mod hls;
+mod http;
+mod local;
+mod playlist_file;
+mod retry;
+mod saved;
+mod soundcloud;
 pub mod youtube;
+mod youtube_url;
+mod ytmusic;
 
+use crate::metrics::Metrics;
+use crate::network::Network;
+pub use audio_cache::AudioCache;
+pub use cache::ResolverCache;
+pub use http::HttpSource;
+pub use local::LocalSource;
+pub use playlist_file::{PlaylistFileSource, PlaylistFormat};
 use reqwest::Client as HttpClient;
+pub use retry::CircuitBreaker;
+use retry::Failure;
+pub use saved::SavedPlaylistSource;
+use serde::{Deserialize, Serialize};
 use serenity::async_trait;
+use serenity::prelude::TypeMapKey;
 use songbird::input::Input;
+pub use soundcloud::SoundCloudSource;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info_span, warn, Instrument};
 pub use youtube::*;
+pub use youtube_url::YoutubeUrl;
 
 type Result<T> = std::result::Result<T, SourceError>;
 
-pub struct SourceFactory;
+// where a query is looked up, chosen with a "prefix:" in front of it
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchSource {
+    #[default]
+    Youtube,
+    YoutubeMusic,
+    SoundCloud,
+    Local,
+    Url,
+}
+
+impl SearchSource {
+    pub const ALL: [SearchSource; 5] = [
+        SearchSource::Youtube,
+        SearchSource::YoutubeMusic,
+        SearchSource::SoundCloud,
+        SearchSource::Local,
+        SearchSource::Url,
+    ];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            SearchSource::Youtube => "yt",
+            SearchSource::YoutubeMusic => "ytm",
+            SearchSource::SoundCloud => "sc",
+            SearchSource::Local => "local",
+            SearchSource::Url => "url",
+        }
+    }
+
+    // splits "yt:query" into the source and the query
+    fn split_prefix(query: &str) -> Option<(SearchSource, &str)> {
+        let (prefix, rest) = query.split_once(':')?;
+        let source = SearchSource::ALL
+            .into_iter()
+            .find(|source| source.prefix().eq_ignore_ascii_case(prefix.trim()))?;
+
+        Some((source, rest.trim()))
+    }
+
+    // links without a prefix are sent to the site they point to
+    fn from_url(url: &str) -> SearchSource {
+        if url.contains("soundcloud.com") {
+            SearchSource::SoundCloud
+        } else if YoutubeUrl::parse(url).is_some() {
+            SearchSource::Youtube
+        } else {
+            SearchSource::Url
+        }
+    }
+}
+
+impl fmt::Display for SearchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+impl FromStr for SearchSource {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "youtube" => Ok(SearchSource::Youtube),
+            "youtubemusic" => Ok(SearchSource::YoutubeMusic),
+            "soundcloud" => Ok(SearchSource::SoundCloud),
+            name => SearchSource::ALL
+                .into_iter()
+                .find(|source| source.prefix() == name)
+                .ok_or_else(|| format!("unknown source {name}")),
+        }
+    }
+}
+
+// what was resolved or downloaded once, shared by every guild
+#[derive(Clone)]
+pub struct SourceCaches {
+    pub resolver: ResolverCache,
+    // audio of YouTube videos on disk, turned off without a directory
+    pub audio: Option<AudioCache>,
+}
+
+// creates the source matching the prefix of a query
+#[derive(Clone)]
+pub struct SourceFactory {
+    client: HttpClient,
+    // used for queries without a prefix that aren't links
+    default: SearchSource,
+    // only files in this directory can be played, local files are disabled without it
+    local_dir: Option<PathBuf>,
+    caches: SourceCaches,
+    metrics: Metrics,
+    network: Arc<Network>,
+    // stops YouTube lookups of the guild while they keep being rejected
+    breaker: CircuitBreaker,
+}
 
 impl SourceFactory {
-    pub async fn new(query: &str, client: HttpClient) -> Result<Box<dyn TrackSource>> {
-        // Default source is Youtube
-        let source = YoutubeSource::new(query, client).await?;
+    pub fn new(
+        client: HttpClient,
+        default: SearchSource,
+        local_dir: Option<PathBuf>,
+        caches: SourceCaches,
+        metrics: Metrics,
+        network: Arc<Network>,
+        breaker: CircuitBreaker,
+    ) -> Self {
+        SourceFactory {
+            client,
+            default,
+            local_dir,
+            caches,
+            metrics,
+            network,
+            breaker,
+        }
+    }
+
+    // the tracks of sources created for commands are counted in the metrics
+    pub async fn create(&self, query: &str) -> Result<Box<dyn TrackSource>> {
+        let query = query.trim();
+
+        let started = Instant::now();
+        match self.create_nested(query).await {
+            Ok(source) => Ok(Box::new(MeasuredSource {
+                source,
+                query: query.to_string(),
+                metrics: self.metrics.clone(),
+                created: Some(started.elapsed()),
+            })),
+            Err(err) => {
+                warn!(query, error = err.name(), "source not created");
+                self.metrics.resolved(started.elapsed(), Some(&err));
+                Err(err)
+            }
+        }
+    }
+
+    // counts the tracks of a source a command made itself, like of a saved playlist
+    pub fn measure(&self, name: &str, source: Box<dyn TrackSource>) -> Box<dyn TrackSource> {
+        Box::new(MeasuredSource {
+            source,
+            query: name.to_string(),
+            metrics: self.metrics.clone(),
+            created: None,
+        })
+    }
+
+    // for sources looking up queries of their own, like playlists, whose tracks are
+    // already counted as theirs
+    async fn create_nested(&self, query: &str) -> Result<Box<dyn TrackSource>> {
+        let query = query.trim();
+
+        if let Some(format) = PlaylistFormat::from_name(query).filter(|_| query.starts_with("http"))
+        {
+            let base = reqwest::Url::parse(query).ok();
+            return PlaylistFileSource::open(query, format, base, &self.client, self.clone()).await;
+        }
+
+        let (source, query) = match SearchSource::split_prefix(query) {
+            Some(prefixed) => prefixed,
+            None if query.starts_with("http") => (SearchSource::from_url(query), query),
+            None => (self.default, query),
+        };
+
+        self.search(source, query).await
+    }
+
+    async fn search(&self, source: SearchSource, query: &str) -> Result<Box<dyn TrackSource>> {
+        let (client, caches, network, breaker) = (
+            self.client.clone(),
+            self.caches.clone(),
+            self.network.clone(),
+            self.breaker.clone(),
+        );
+        let source: Box<dyn TrackSource> = match source {
+            SearchSource::Youtube => {
+                Box::new(YoutubeSource::new(query, caches, network, breaker).await?)
+            }
+            SearchSource::YoutubeMusic => {
+                Box::new(YoutubeSource::from_music(query, client, caches, network, breaker).await?)
+            }
+            SearchSource::SoundCloud => {
+                Box::new(SoundCloudSource::new(query, client, caches.resolver).await?)
+            }
+            SearchSource::Local => {
+                let dir = self.local_dir.as_deref().ok_or(SourceError::Other)?;
+
+                Box::new(LocalSource::new(query, dir).await?)
+            }
+            SearchSource::Url => Box::new(HttpSource::new(query, client)?),
+        };
+
+        Ok(source)
+    }
+}
+
+// times and logs every track the source looks up, the first one includes the search
+// done when the source was created
+struct MeasuredSource {
+    source: Box<dyn TrackSource>,
+    query: String,
+    metrics: Metrics,
+    created: Option<Duration>,
+}
+
+#[async_trait]
+impl TrackSource for MeasuredSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        let span = info_span!("resolve", query = self.query);
+
+        let started = Instant::now();
+        let track = self.source.next().instrument(span.clone()).await?;
 
-        Ok(Box::new(source))
+        let latency = started.elapsed() + self.created.take().unwrap_or_default();
+        self.metrics.resolved(latency, track.as_ref().err());
+
+        match &track {
+            Ok(track) => {
+                debug!(parent: &span, title = track.metadata.title, ?latency, "track resolved")
+            }
+            Err(err) => warn!(parent: &span, error = err.name(), ?latency, "track not resolved"),
+        }
+
+        Some(track)
     }
 }
 
 // TODO: More Errors
 pub enum SourceError {
+    // YouTube kept rejecting the requests
+    Rejected,
+    // lookups of the guild are paused by its circuit breaker
+    Unavailable,
     Other,
 }
 
+impl SourceError {
+    // the rest of the source's songs would fail the same way
+    pub fn ends_source(&self) -> bool {
+        matches!(self, SourceError::Unavailable)
+    }
+
+    // label of the variant in the metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceError::Rejected => "rejected",
+            SourceError::Unavailable => "unavailable",
+            SourceError::Other => "other",
+        }
+    }
+}
+
+impl From<Failure> for SourceError {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Rejected => SourceError::Rejected,
+            Failure::Transient | Failure::Fatal => SourceError::Other,
+        }
+    }
+}
+
 #[async_trait]
 // trait for getting next tracks from the source
 pub trait TrackSource: Send {
@@ -32,27 +310,122 @@ pub trait TrackSource: Send {
 pub struct TrackInfo {
     pub metadata: Metadata,
     pub input: Input,
+    // position the track is seeked to once queued
+    pub start: Option<Duration>,
+    // position the track is stopped at
+    pub end: Option<Duration>,
 }
 
 impl TrackInfo {
     fn new(metadata: Metadata, input: Input) -> Self {
-        TrackInfo { metadata, input }
+        TrackInfo {
+            metadata,
+            input,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    Youtube,
+    SoundCloud,
+    Local,
+    Http,
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceKind::Youtube => f.write_str("YouTube"),
+            SourceKind::SoundCloud => f.write_str("SoundCloud"),
+            SourceKind::Local => f.write_str("Local file"),
+            SourceKind::Http => f.write_str("Link"),
+        }
+    }
+}
+
+// length of a track in json, in seconds and null for live streams
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonDuration(pub Option<f64>);
+
+impl From<Option<Duration>> for JsonDuration {
+    fn from(duration: Option<Duration>) -> Self {
+        JsonDuration(duration.map(|duration| duration.as_secs_f64()))
     }
 }
 
 #[derive(Clone)]
 pub struct Metadata {
     pub title: String,
+    // link to the track, or a query with a prefix that finds it again
     pub url: String,
     pub thumbnail: String,
+    // None for live streams
+    pub duration: Option<Duration>,
+    // uploader or channel of the track
+    pub artist: Option<String>,
+    pub artist_url: Option<String>,
+    pub views: Option<u64>,
+    pub upload_date: Option<String>,
+    pub is_live: bool,
+    pub kind: SourceKind,
+    // urls of similar tracks, used by autoplay
+    pub related: Vec<String>,
+    // sorted by their start
+    pub chapters: Vec<Chapter>,
+}
+
+// stored on the track so commands can tell what is playing
+impl TypeMapKey for Metadata {
+    type Value = Metadata;
 }
 
 impl Metadata {
-    fn new(title: String, url: String, thumbnail: String) -> Self {
+    // sources fill in the rest of the fields they know about
+    fn new(title: String, url: String, thumbnail: String, kind: SourceKind) -> Self {
         Metadata {
             title,
             url,
             thumbnail,
+            duration: None,
+            artist: None,
+            artist_url: None,
+            views: None,
+            upload_date: None,
+            is_live: false,
+            kind,
+            related: Vec::new(),
+            chapters: Vec::new(),
+        }
+    }
+
+    // url that can be opened in a browser, local files don't have one
+    pub fn link(&self) -> Option<&str> {
+        self.url.starts_with("http").then_some(self.url.as_str())
+    }
+}
+
+#[derive(Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+}
+
+// "90", "1:30" or "1:01:30"
+pub fn parse_timestamp(time: &str) -> Option<Duration> {
+    let mut secs: u64 = 0;
+
+    for (i, part) in time.split(':').enumerate() {
+        if i > 2 || part.is_empty() {
+            return None;
         }
+        secs = secs
+            .checked_mul(60)?
+            .checked_add(part.parse::<u64>().ok()?)?;
     }
+
+    Some(Duration::from_secs(secs))
 }
@@ -0,0 +1,530 @@
+use super::{
+    HttpSource, Result, SourceError as Error, SourceFactory, SourceKind, TrackInfo, TrackSource,
+};
+use reqwest::{Client as HttpClient, Url};
+use serenity::async_trait;
+use std::time::Duration;
+use std::vec::IntoIter;
+
+// cue sheets count time in frames of audio CDs
+const CUE_FRAMES_PER_SEC: u64 = 75;
+// larger files aren't downloaded
+const MAX_PLAYLIST_LEN: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+    Xspf,
+    Cue,
+}
+
+impl PlaylistFormat {
+    // by the extension of a file name or the path of a link
+    pub fn from_name(name: &str) -> Option<PlaylistFormat> {
+        let path = name.split(['?', '#']).next()?;
+        let (_, extension) = path.rsplit_once('.')?;
+
+        match extension.to_ascii_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            "cue" => Some(PlaylistFormat::Cue),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct Entry {
+    // link or path of the file, paths are relative to the playlist
+    location: Option<String>,
+    // "artist - title" when the playlist has it
+    title: Option<String>,
+    // part of the file for tracks of cue sheets
+    start: Option<Duration>,
+    end: Option<Duration>,
+}
+
+// songs listed in an uploaded or linked playlist file. Links are played from their site,
+// anything else is searched on YouTube by its title
+pub struct PlaylistFileSource {
+    entries: IntoIter<Entry>,
+    // relative paths are resolved against it, uploaded files don't have one
+    base: Option<Url>,
+    factory: SourceFactory,
+}
+
+impl PlaylistFileSource {
+    // hls streams are m3u8 files too, but their entries are parts of one stream, which is
+    // played like a direct link
+    pub async fn open(
+        url: &str,
+        format: PlaylistFormat,
+        base: Option<Url>,
+        client: &HttpClient,
+        factory: SourceFactory,
+    ) -> Result<Box<dyn TrackSource>> {
+        let text = download(url, client).await.ok_or(Error::Other)?;
+
+        let entries = match format {
+            PlaylistFormat::M3u if text.contains("#EXT-X-") => {
+                return Ok(Box::new(HttpSource::hls(url, client.clone())?));
+            }
+            PlaylistFormat::M3u => parse_m3u(&text),
+            PlaylistFormat::Pls => parse_pls(&text),
+            PlaylistFormat::Xspf => parse_xspf(&text),
+            PlaylistFormat::Cue => parse_cue(&text),
+        };
+
+        if entries.is_empty() {
+            return Err(Error::Other);
+        }
+
+        Ok(Box::new(PlaylistFileSource {
+            entries: entries.into_iter(),
+            base,
+            factory,
+        }))
+    }
+
+    // only links to sites are played, playlists inside playlists are not
+    fn resolve(&self, location: &str) -> Option<String> {
+        let location = location.replace('\\', "/");
+
+        let url = match Url::parse(&location) {
+            Ok(url) => url,
+            Err(_err) => self.base.as_ref()?.join(&location).ok()?,
+        };
+
+        let playable = matches!(url.scheme(), "http" | "https")
+            && PlaylistFormat::from_name(url.path()).is_none();
+
+        playable.then(|| url.to_string())
+    }
+}
+
+#[async_trait]
+impl TrackSource for PlaylistFileSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        let entry = self.entries.next()?;
+
+        let url = entry
+            .location
+            .as_deref()
+            .and_then(|location| self.resolve(location));
+        // offsets of cue tracks only mean something in the file of the cue sheet
+        let resolved = url.is_some();
+        let title = entry
+            .title
+            .clone()
+            .or_else(|| entry.location.as_deref().map(file_stem));
+
+        let query = match (url, &title) {
+            (Some(url), _) => url,
+            (None, Some(title)) => format!("yt:{title}"),
+            (None, None) => return Some(Err(Error::Other)),
+        };
+
+        let mut source = match self.factory.create_nested(&query).await {
+            Ok(source) => source,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut track = match source.next().await? {
+            Ok(track) => track,
+            Err(err) => return Some(Err(err)),
+        };
+
+        // files only have their name, tracks of a cue sheet share one file
+        let named_by_file = matches!(track.metadata.kind, SourceKind::Http | SourceKind::Local);
+        if let Some(title) = title.filter(|_| named_by_file) {
+            track.metadata.title = title;
+        }
+        if resolved && entry.start.is_some() {
+            track.start = entry.start;
+            track.end = entry.end;
+        }
+
+        Some(Ok(track))
+    }
+}
+
+// name of the file without its directory and extension
+fn file_stem(location: &str) -> String {
+    let name = location.rsplit(['/', '\\']).next().unwrap_or(location);
+
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+// "#EXTINF:123,Artist - Title" comes before the location it describes
+fn parse_m3u(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut title = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(Entry {
+                location: Some(line.to_string()),
+                title: title.take(),
+                ..Default::default()
+            });
+        }
+    }
+
+    entries
+}
+
+// "File1=..." and "Title1=..." keys, numbered from 1
+fn parse_pls(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<(usize, Entry)> = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let (name, number) =
+            key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+        let Ok(number) = number.parse::<usize>() else {
+            continue;
+        };
+
+        let index = match entries.iter().position(|(n, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                entries.push((number, Entry::default()));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index].1;
+
+        match name.to_ascii_lowercase().as_str() {
+            "file" => entry.location = Some(value.trim().to_string()),
+            "title" => {
+                entry.title = Some(value.trim().to_string()).filter(|title| !title.is_empty())
+            }
+            _ => {}
+        }
+    }
+
+    entries.sort_by_key(|(number, _)| *number);
+    entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .filter(|entry| entry.location.is_some())
+        .collect()
+}
+
+// <track> elements with <location>, <title> and <creator>
+fn parse_xspf(text: &str) -> Vec<Entry> {
+    xml_elements(text, "track")
+        .into_iter()
+        .map(|track| {
+            let field = |tag| {
+                xml_elements(track, tag)
+                    .first()
+                    .map(|value| xml_unescape(value.trim()))
+                    .filter(|value| !value.is_empty())
+            };
+
+            let title = match (field("creator"), field("title")) {
+                (Some(creator), Some(title)) => Some(format!("{creator} - {title}")),
+                (creator, title) => title.or(creator),
+            };
+
+            Entry {
+                location: field("location"),
+                title,
+                ..Default::default()
+            }
+        })
+        .filter(|entry| entry.location.is_some() || entry.title.is_some())
+        .collect()
+}
+
+// every track of a cue sheet starts at its "INDEX 01" and ends where the next track of the
+// same file starts
+fn parse_cue(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut file = None;
+    let mut performer = None;
+
+    for line in text.lines().map(str::trim) {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => file = Some(cue_string(rest)),
+            "TRACK" => entries.push(Entry {
+                location: file.clone(),
+                ..Default::default()
+            }),
+            // before the first track it is the performer of the album
+            "PERFORMER" => match entries.last_mut() {
+                Some(entry) => {
+                    let title = entry.title.take().unwrap_or_default();
+                    entry.title = Some(format!("{} - {title}", cue_string(rest)));
+                }
+                None => performer = Some(cue_string(rest)),
+            },
+            "TITLE" => {
+                if let Some(entry) = entries.last_mut() {
+                    let title = cue_string(rest);
+                    entry.title = Some(match entry.title.take() {
+                        // the performer of the track came first
+                        Some(performer) => format!("{performer}{title}"),
+                        None => title,
+                    });
+                }
+            }
+            "INDEX" => {
+                let Some(("01", time)) = rest.split_once(' ') else {
+                    continue;
+                };
+                if let Some(entry) = entries.last_mut() {
+                    entry.start = cue_time(time.trim());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // tracks without their own performer get the album's one
+    if let Some(performer) = performer {
+        for entry in &mut entries {
+            if let Some(title) = entry.title.as_mut().filter(|title| !title.contains(" - ")) {
+                *title = format!("{performer} - {title}");
+            }
+        }
+    }
+
+    for i in 1..entries.len() {
+        if entries[i].location == entries[i - 1].location {
+            entries[i - 1].end = entries[i].start;
+        }
+    }
+
+    entries
+}
+
+// values can be quoted to have spaces
+fn cue_string(value: &str) -> String {
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or(quoted),
+        // FILE lines end with the type of the file
+        None => value.split_whitespace().next().unwrap_or(value),
+    };
+
+    value.to_string()
+}
+
+// the text of the file, None when it's larger than allowed
+async fn download(url: &str, client: &HttpClient) -> Option<String> {
+    let mut response = client.get(url).send().await.ok()?.error_for_status().ok()?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_PLAYLIST_LEN as u64)
+    {
+        return None;
+    }
+
+    // the length isn't always sent
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await.ok()? {
+        if data.len() + chunk.len() > MAX_PLAYLIST_LEN {
+            return None;
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+// "mm:ss:ff"
+fn cue_time(time: &str) -> Option<Duration> {
+    let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (mins, secs, frames) = (parts.next()??, parts.next()??, parts.next()??);
+
+    let secs = mins.checked_mul(60)?.checked_add(secs)?;
+    let millis = frames.checked_mul(1000)? / CUE_FRAMES_PER_SEC;
+
+    Duration::from_secs(secs).checked_add(Duration::from_millis(millis))
+}
+
+// contents of every element with the tag, they can't be nested in each other
+fn xml_elements<'a>(text: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}"), format!("</{tag}>"));
+    let mut elements = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+
+        // "<tracklist>" starts with "<track" too
+        if !after.starts_with(['>', ' ', '\t', '\r', '\n']) {
+            rest = after;
+            continue;
+        }
+
+        let Some(content_start) = after.find('>') else {
+            break;
+        };
+        let content = &after[content_start + 1..];
+        let Some(end) = content.find(&close) else {
+            break;
+        };
+
+        elements.push(&content[..end]);
+        rest = &content[end + close.len()..];
+    }
+
+    elements
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location: &str, title: Option<&str>) -> Entry {
+        Entry {
+            location: Some(location.to_string()),
+            title: title.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn m3u() {
+        let text = "#EXTM3U\n\
+            #EXTINF:215,Artist - Song\n\
+            https://example.com/song.mp3\n\
+            \n\
+            # comment\n\
+            music/other.flac\n";
+
+        assert_eq!(
+            parse_m3u(text),
+            [
+                entry("https://example.com/song.mp3", Some("Artist - Song")),
+                entry("music/other.flac", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn m3u_empty_title() {
+        let text = "#EXTINF:-1,\nhttps://example.com/stream\n";
+
+        assert_eq!(parse_m3u(text), [entry("https://example.com/stream", None)]);
+    }
+
+    #[test]
+    fn pls() {
+        let text = "[playlist]\n\
+            File2=https://example.com/second.mp3\n\
+            Title1=First\n\
+            File1=https://example.com/first.mp3\n\
+            Title3=No file\n\
+            NumberOfEntries=3\n\
+            Version=2\n";
+
+        assert_eq!(
+            parse_pls(text),
+            [
+                entry("https://example.com/first.mp3", Some("First")),
+                entry("https://example.com/second.mp3", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <trackList>
+                <track>
+                  <location>https://example.com/a.ogg</location>
+                  <creator>Artist</creator>
+                  <title>Rock &amp; Roll</title>
+                </track>
+                <track>
+                  <title>Only a title</title>
+                </track>
+                <track></track>
+              </trackList>
+            </playlist>"#;
+
+        assert_eq!(
+            parse_xspf(text),
+            [
+                entry("https://example.com/a.ogg", Some("Artist - Rock & Roll")),
+                Entry {
+                    title: Some("Only a title".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cue() {
+        let text = r#"PERFORMER "Album Artist"
+            TITLE "Album"
+            FILE "album.flac" WAVE
+              TRACK 01 AUDIO
+                TITLE "Intro"
+                INDEX 01 00:00:00
+              TRACK 02 AUDIO
+                TITLE "Second"
+                PERFORMER "Guest"
+                INDEX 00 03:58:00
+                INDEX 01 04:00:37
+            FILE "bonus.flac" WAVE
+              TRACK 03 AUDIO
+                TITLE "Bonus"
+                INDEX 01 00:00:00"#;
+
+        let entries = parse_cue(text);
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    start: Some(Duration::ZERO),
+                    end: Some(Duration::from_millis(240_493)),
+                    ..entry("album.flac", Some("Album Artist - Intro"))
+                },
+                Entry {
+                    start: Some(Duration::from_millis(240_493)),
+                    ..entry("album.flac", Some("Guest - Second"))
+                },
+                Entry {
+                    start: Some(Duration::ZERO),
+                    ..entry("bonus.flac", Some("Album Artist - Bonus"))
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cue_time_overflow() {
+        assert_eq!(cue_time("01:02:15"), Some(Duration::from_millis(62_200)));
+        assert_eq!(cue_time("999999999999999999:00:00"), None);
+        assert_eq!(cue_time("00:00:99999999999999999"), None);
+    }
+}
@@ -0,0 +1,234 @@
+use reqwest::StatusCode;
+use rusty_ytdl::VideoError;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// tries of a request before giving up
+const ATTEMPTS: u32 = 4;
+// waited after the first failure, doubled after every next one
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(8);
+// rejections in a row that open the breaker, and for how long it stays open
+const BREAKER_THRESHOLD: u32 = 5;
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+// what the page says when YouTube wants the bot to sign in first
+const BOT_CHECK_TEXTS: [&str; 2] = [
+    "Sign in to confirm you’re not a bot",
+    "Sign in to confirm you're not a bot",
+];
+
+// what a failed request to YouTube says about trying it again
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Failure {
+    // network errors and 5xx
+    Transient,
+    // 429 or the sign in page, YouTube limiting the bot
+    Rejected,
+    // like private, removed, rented or not yet aired videos
+    Fatal,
+}
+
+impl Failure {
+    // rusty_ytdl reads pages without looking at their status, so errors about what they hold
+    // may be YouTube refusing the bot. Those are None, `of_response` tells them apart
+    pub fn of_video(err: &VideoError) -> Option<Self> {
+        match err {
+            // errors of sending the request or reading its body
+            VideoError::ReqwestMiddleware(_) | VideoError::BodyCannotParsed => {
+                Some(Failure::Transient)
+            }
+            VideoError::VideoNotFound
+            | VideoError::VideoIsPrivate
+            | VideoError::VideoSourceNotFound => None,
+            // like formats missing or the client not being built
+            _ => Some(Failure::Fatal),
+        }
+    }
+
+    // a page of YouTube fetched by the bot itself, an ordinary one means the error was real,
+    // like a private, rented or not yet aired video
+    pub fn of_response(status: StatusCode, body: &str) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || BOT_CHECK_TEXTS.iter().any(|text| body.contains(text))
+        {
+            Failure::Rejected
+        } else if status.is_server_error() {
+            Failure::Transient
+        } else {
+            Failure::Fatal
+        }
+    }
+}
+
+// runs the request until it works, fails in a way not worth trying again or runs out of
+// attempts. The waits are randomized so guilds failing together don't retry together
+pub async fn with_retries<T, F, Fut>(mut request: F) -> Result<T, Failure>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Failure>>,
+{
+    let mut attempt = 0;
+
+    loop {
+        let failure = match request().await {
+            Ok(value) => return Ok(value),
+            Err(failure) => failure,
+        };
+
+        attempt += 1;
+        if failure == Failure::Fatal || attempt == ATTEMPTS {
+            return Err(failure);
+        }
+
+        tokio::time::sleep(backoff(attempt)).await;
+    }
+}
+
+// half of the delay is fixed and the other half random
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << (attempt - 1)).min(MAX_DELAY);
+
+    delay / 2 + delay.mul_f64(rand::random::<f64>() / 2.0)
+}
+
+// stops a guild from resolving songs for a while when YouTube keeps rejecting it, instead
+// of making it worse. Once the cooldown passes requests are let through again and the
+// first rejection opens it right away
+#[derive(Clone, Default)]
+pub struct CircuitBreaker(Arc<Mutex<BreakerState>>);
+
+#[derive(Default)]
+struct BreakerState {
+    rejections: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn is_open(&self) -> bool {
+        let state = self.0.lock().unwrap();
+
+        state.open_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    pub fn record(&self, result: Result<(), Failure>) {
+        let mut state = self.0.lock().unwrap();
+
+        match result {
+            Ok(()) => *state = BreakerState::default(),
+            Err(Failure::Rejected) => {
+                state.rejections += 1;
+                if state.rejections >= BREAKER_THRESHOLD {
+                    state.open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+                }
+            }
+            // not YouTube's answer about the bot
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_errors() {
+        assert_eq!(
+            Failure::of_video(&VideoError::BodyCannotParsed),
+            Some(Failure::Transient)
+        );
+        assert_eq!(
+            Failure::of_video(&VideoError::FormatNotFound),
+            Some(Failure::Fatal)
+        );
+        assert_eq!(Failure::of_video(&VideoError::VideoIsPrivate), None);
+        assert_eq!(Failure::of_video(&VideoError::VideoSourceNotFound), None);
+    }
+
+    #[test]
+    fn responses() {
+        assert_eq!(
+            Failure::of_response(StatusCode::TOO_MANY_REQUESTS, ""),
+            Failure::Rejected
+        );
+        assert_eq!(
+            Failure::of_response(
+                StatusCode::OK,
+                "<div>Sign in to confirm you’re not a bot</div>"
+            ),
+            Failure::Rejected
+        );
+        assert_eq!(
+            Failure::of_response(StatusCode::SERVICE_UNAVAILABLE, ""),
+            Failure::Transient
+        );
+        assert_eq!(
+            Failure::of_response(StatusCode::OK, "<html></html>"),
+            Failure::Fatal
+        );
+        assert_eq!(
+            Failure::of_response(StatusCode::NOT_FOUND, ""),
+            Failure::Fatal
+        );
+    }
+
+    #[test]
+    fn backoff_bounds() {
+        for attempt in 1..10 {
+            let delay = BASE_DELAY.saturating_mul(1 << (attempt - 1)).min(MAX_DELAY);
+
+            for _ in 0..20 {
+                let wait = backoff(attempt);
+                assert!(wait >= delay / 2 && wait <= delay, "{attempt}: {wait:?}");
+            }
+        }
+        assert!(backoff(30) <= MAX_DELAY);
+    }
+
+    #[test]
+    fn breaker_opens_after_rejections() {
+        let breaker = CircuitBreaker::default();
+
+        for _ in 1..BREAKER_THRESHOLD {
+            breaker.record(Err(Failure::Rejected));
+        }
+        // other failures don't count
+        breaker.record(Err(Failure::Transient));
+        breaker.record(Err(Failure::Fatal));
+        assert!(!breaker.is_open());
+
+        breaker.record(Err(Failure::Rejected));
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn breaker_half_open() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..BREAKER_THRESHOLD {
+            breaker.record(Err(Failure::Rejected));
+        }
+
+        // the cooldown passed
+        breaker.0.lock().unwrap().open_until = Some(Instant::now() - Duration::from_secs(1));
+        assert!(!breaker.is_open());
+
+        // the first rejection opens it again
+        breaker.record(Err(Failure::Rejected));
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn breaker_closes_on_success() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..BREAKER_THRESHOLD {
+            breaker.record(Err(Failure::Rejected));
+        }
+
+        breaker.record(Ok(()));
+        assert!(!breaker.is_open());
+
+        breaker.record(Err(Failure::Rejected));
+        assert!(!breaker.is_open());
+    }
+}
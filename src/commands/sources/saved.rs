@@ -0,0 +1,46 @@
+use super::{Result, SourceError as Error, SourceFactory, TrackInfo, TrackSource};
+use crate::db::SavedTrack;
+use serenity::async_trait;
+use std::vec::IntoIter;
+
+// tracks of a playlist saved in the database or an imported queue, each resolved again
+// from its url
+pub struct SavedPlaylistSource {
+    tracks: IntoIter<SavedTrack>,
+    factory: SourceFactory,
+    // of the entry being played, links of playlists give more than one track
+    current: Option<Box<dyn TrackSource>>,
+}
+
+impl SavedPlaylistSource {
+    pub fn new(tracks: Vec<SavedTrack>, factory: SourceFactory) -> Self {
+        SavedPlaylistSource {
+            tracks: tracks.into_iter(),
+            factory,
+            current: None,
+        }
+    }
+}
+
+#[async_trait]
+impl TrackSource for SavedPlaylistSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        if let Some(source) = &mut self.current {
+            if let Some(track) = source.next().await {
+                return Some(track);
+            }
+            self.current = None;
+        }
+
+        let track = self.tracks.next()?;
+
+        let mut source = match self.factory.create_nested(&track.url).await {
+            Ok(source) => source,
+            Err(err) => return Some(Err(err)),
+        };
+        let first = source.next().await.unwrap_or(Err(Error::Other));
+        self.current = Some(source);
+
+        Some(first)
+    }
+}
@@ -0,0 +1,148 @@
+use super::cache::ResolverCache;
+use super::hls::HlsRequest;
+use super::{Metadata, Result, SourceError as Error, SourceKind, TrackInfo, TrackSource};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use serenity::async_trait;
+use songbird::input::{HttpRequest, Input};
+use std::sync::Arc;
+use std::time::Duration;
+use std::vec::IntoIter;
+use tokio::process::Command;
+
+// tracks and sets from SoundCloud, resolved by yt-dlp
+pub struct SoundCloudSource {
+    tracks: IntoIter<Entry>,
+    client: HttpClient,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Output {
+    Set { entries: Vec<Option<Entry>> },
+    Track(Entry),
+}
+
+#[derive(Deserialize, Clone)]
+struct Entry {
+    title: String,
+    webpage_url: String,
+    thumbnail: Option<String>,
+    duration: Option<f64>,
+    uploader: Option<String>,
+    uploader_url: Option<String>,
+    view_count: Option<u64>,
+    // YYYYMMDD
+    upload_date: Option<String>,
+    #[serde(default)]
+    formats: Vec<Format>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Format {
+    url: String,
+    protocol: Option<String>,
+    acodec: Option<String>,
+    abr: Option<f64>,
+}
+
+impl SoundCloudSource {
+    pub async fn new(query: &str, client: HttpClient, cache: ResolverCache) -> Result<Self> {
+        let target = if query.starts_with("http") {
+            query.to_string()
+        } else {
+            format!("scsearch1:{query}")
+        };
+
+        let key = format!("sc:{target}");
+        let tracks = match cache.get::<Vec<Entry>>(&key) {
+            Some(tracks) => tracks,
+            None => {
+                let tracks = Arc::new(SoundCloudSource::resolve(&target).await?);
+                cache.insert(key, tracks.clone());
+
+                tracks
+            }
+        };
+
+        Ok(SoundCloudSource {
+            tracks: Vec::clone(&tracks).into_iter(),
+            client,
+        })
+    }
+
+    async fn resolve(target: &str) -> Result<Vec<Entry>> {
+        let output = Command::new("yt-dlp")
+            .args(["-J", target])
+            .output()
+            .await
+            .map_err(|_err| Error::Other)?;
+
+        let tracks = match serde_json::from_slice(&output.stdout).map_err(|_err| Error::Other)? {
+            Output::Set { entries } => entries.into_iter().flatten().collect(),
+            Output::Track(entry) => vec![entry],
+        };
+
+        Ok(tracks)
+    }
+
+    // direct downloads are preferred, most tracks only have HLS playlists of mp3 segments
+    fn input(&self, formats: &[Format]) -> Option<Input> {
+        let audio = |format: &&Format| format.acodec.as_deref() != Some("none");
+        let bitrate = |format: &&Format| format.abr.unwrap_or_default() as u64;
+
+        let progressive = formats
+            .iter()
+            .filter(audio)
+            .filter(|format| matches!(format.protocol.as_deref(), Some("http" | "https")))
+            .max_by_key(bitrate);
+        if let Some(format) = progressive {
+            return Some(HttpRequest::new(self.client.clone(), format.url.clone()).into());
+        }
+
+        let hls = formats
+            .iter()
+            .filter(audio)
+            .filter(|format| format.acodec.as_deref() == Some("mp3"))
+            .filter(|format| {
+                format
+                    .protocol
+                    .as_deref()
+                    .is_some_and(|protocol| protocol.starts_with("m3u8"))
+            })
+            .max_by_key(bitrate)?;
+        let request = HlsRequest::new(self.client.clone(), hls.url.clone(), "mp3");
+
+        Some(Input::Lazy(Box::new(request)))
+    }
+}
+
+#[async_trait]
+impl TrackSource for SoundCloudSource {
+    async fn next(&mut self) -> Option<Result<TrackInfo>> {
+        let entry = self.tracks.next()?;
+
+        let Some(input) = self.input(&entry.formats) else {
+            return Some(Err(Error::Other));
+        };
+
+        let metadata = Metadata {
+            duration: entry.duration.map(Duration::from_secs_f64),
+            artist: entry.uploader,
+            artist_url: entry.uploader_url,
+            views: entry.view_count,
+            upload_date: entry
+                .upload_date
+                .filter(|date| date.len() == 8)
+                .map(|date| format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])),
+            ..Metadata::new(
+                entry.title,
+                entry.webpage_url,
+                entry.thumbnail.unwrap_or_default(),
+                SourceKind::SoundCloud,
+            )
+        };
+
+        Some(Ok(TrackInfo::new(metadata, input)))
+    }
+}
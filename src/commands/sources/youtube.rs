@@ -1,18 +1,35 @@
-use super::{Metadata, Result, SourceError as Error, TrackInfo, TrackSource};
+use super::audio_cache::AudioCache;
+use super::fallback::FallbackRequest;
+use super::hls::HlsRequest;
+use super::retry::{with_retries, CircuitBreaker, Failure};
+use super::youtube_url::YoutubeUrl;
+use super::ytmusic::search_song;
+use super::{
+    parse_timestamp, Chapter, Metadata, Result, SourceCaches, SourceError as Error, SourceKind,
+    TrackInfo, TrackSource,
+};
+use crate::network::{Network, Route};
 use reqwest::header::HeaderMap;
 use reqwest::Client as HttpClient;
 use rusty_ytdl as ytdl;
 use serde::Deserialize;
 use serenity::async_trait;
-use songbird::input::{HttpRequest, Input};
+use songbird::input::{File as FileInput, HttpRequest, Input};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::Duration;
 use std::vec::IntoIter;
 use tokio::process::Command;
+use tracing::debug;
+use url::Url;
 use ytdl::{
     search::{SearchOptions, SearchResult::*, SearchType, YouTube},
-    DownloadOptions, RequestOptions, Thumbnail, VideoError, VideoOptions, VideoQuality,
-    VideoSearchOptions,
+    DownloadOptions, RequestOptions, Thumbnail, VideoError, VideoFormat, VideoInfo, VideoOptions,
+    VideoQuality, VideoSearchOptions,
 };
 
+const SEARCH_URL: &str = "https://www.youtube.com/results";
+
 static SEARCH_OPTIONS: Option<&SearchOptions> = Some(&SearchOptions {
     limit: 1,
     search_type: SearchType::Video,
@@ -32,6 +49,18 @@ static VIDEO_OPTIONS: VideoOptions = VideoOptions {
     },
 };
 
+// rusty_ytdl doesn't look at the status of YouTube's answers, so when a page didn't hold what
+// it should, it is fetched again to tell YouTube refusing the bot apart
+async fn check_page(client: &HttpClient, url: &str) -> Failure {
+    let Ok(response) = client.get(url).send().await else {
+        return Failure::Transient;
+    };
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+
+    Failure::of_response(status, &body)
+}
+
 // get best quality thumbnail
 pub fn choose_thumbnail(mut thumbnails: Vec<Thumbnail>) -> Thumbnail {
     let len = thumbnails.len();
@@ -41,7 +70,12 @@ pub fn choose_thumbnail(mut thumbnails: Vec<Thumbnail>) -> Thumbnail {
 }
 pub struct YoutubeSource {
     tracks: IntoIter<String>,
-    client: HttpClient,
+    caches: SourceCaches,
+    network: Arc<Network>,
+    // of the guild the songs are played in
+    breaker: CircuitBreaker,
+    // timestamp of a single video link
+    start: Option<Duration>,
 }
 
 #[async_trait]
@@ -50,77 +84,232 @@ impl TrackSource for YoutubeSource {
         let url = self.tracks.next()?;
 
         // TODO: Better error handling
-        let video = match ytdl::Video::new_with_options(url, VIDEO_OPTIONS.clone()) {
+        let video = match self.video_info(url).await {
             Ok(video) => video,
-            Err(err) => return Some(Err(Error::Other)),
+            Err(err) => return Some(Err(err)),
         };
+        let info = &video.info;
 
-        let info = match video.get_info().await {
-            Ok(info) => info,
-            Err(err) => return Some(Err(Error::Other)),
-        };
+        let data = &info.video_details;
+
+        // live streams report zero length
+        let duration = data
+            .length_seconds
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+        let is_live = data.is_live_content && duration.is_none();
 
-        let format = match ytdl::choose_format(&info.formats, &VIDEO_OPTIONS) {
-            Ok(format) => format,
-            Err(err) => return Some(Err(Error::Other)),
+        // streamed the way they were resolved
+        let client = self.network.client(&video.route);
+        let input = if is_live {
+            live_input(client, info)
+        } else {
+            video_input(client, info, self.caches.audio.as_ref())
+        };
+        let Some(input) = input else {
+            return Some(Err(Error::Other));
         };
 
-        let data = info.video_details;
-        let metadata = Metadata::new(
-            data.title,
-            data.video_url,
-            choose_thumbnail(data.thumbnails).url,
-        );
+        let related = info
+            .related_videos
+            .iter()
+            .map(|video| video.url.clone())
+            .collect();
 
-        let content_length: Option<u64> = match format.content_length {
-            Some(len) => str::parse::<u64>(&len).ok(),
-            None => None,
+        let mut chapters = data
+            .chapters
+            .iter()
+            .map(|chapter| Chapter {
+                title: chapter.title.clone(),
+                start: Duration::from_secs(chapter.start_time.max(0) as u64),
+            })
+            .collect::<Vec<_>>();
+        if chapters.is_empty() {
+            chapters = description_chapters(&data.description);
+        }
+
+        let (artist, artist_url) = match &data.author {
+            Some(author) => (Some(author.name.clone()), Some(author.channel_url.clone())),
+            None => (Some(data.owner_channel_name.clone()), None),
         };
 
-        let input = HttpRequest {
-            client: self.client.clone(),
-            request: format.url,
-            headers: HeaderMap::default(),
-            content_length,
+        let metadata = Metadata {
+            duration,
+            artist: artist.filter(|name| !name.is_empty()),
+            artist_url: artist_url.filter(|url| !url.is_empty()),
+            views: data.view_count.parse().ok(),
+            // sometimes a full timestamp, only the date is kept
+            upload_date: data
+                .upload_date
+                .get(..10)
+                .map(str::to_string)
+                .filter(|date| !date.is_empty()),
+            is_live,
+            related,
+            chapters,
+            ..Metadata::new(
+                data.title.clone(),
+                data.video_url.clone(),
+                choose_thumbnail(data.thumbnails.clone()).url,
+                SourceKind::Youtube,
+            )
         };
 
-        Some(Ok(TrackInfo::new(metadata, input.into())))
+        // live streams can't be seeked
+        let start = self.start.take().filter(|_| !is_live);
+
+        Some(Ok(TrackInfo {
+            start,
+            ..TrackInfo::new(metadata, input)
+        }))
     }
 }
 
+// stream urls only work from the route that got them
+struct CachedVideo {
+    info: VideoInfo,
+    route: Route,
+}
+
 impl YoutubeSource {
-    pub async fn new(query: &str, client: HttpClient) -> Result<Self> {
-        let tracks = if query.contains("&list=") {
-            YoutubeSource::from_playlist(query).await?
-        } else if query.starts_with("http") {
-            vec![query.to_string()]
-        } else {
-            YoutubeSource::from_query(query).await?
+    // info of videos is shared between guilds until its stream urls get old
+    async fn video_info(&self, url: String) -> Result<Arc<CachedVideo>> {
+        let key = YoutubeUrl::parse(&url)
+            .and_then(|url| url.video_id)
+            .map(|id| format!("yt:{id}"));
+
+        if let Some(video) = key.as_deref().and_then(|key| self.caches.resolver.get(key)) {
+            return Ok(video);
+        }
+        if self.breaker.is_open() {
+            return Err(Error::Unavailable);
+        }
+
+        let route = self.network.next_route();
+        let options = VideoOptions {
+            request_options: self.network.request_options(&route),
+            ..VIDEO_OPTIONS.clone()
+        };
+        let video =
+            ytdl::Video::new_with_options(url.clone(), options).map_err(|_err| Error::Other)?;
+        // rusty_ytdl already retries transient errors and 429s on its own
+        let info = match video.get_info().await {
+            Ok(info) => Ok(info),
+            Err(err) => Err(match Failure::of_video(&err) {
+                Some(failure) => failure,
+                None => check_page(&self.network.client(&route), &url).await,
+            }),
+        };
+        self.breaker
+            .record(info.as_ref().map(|_info| ()).map_err(|failure| *failure));
+        let info = info.map_err(Error::from)?;
+        let video = Arc::new(CachedVideo { info, route });
+
+        // the segments of live streams keep changing
+        if let Some(key) = key.filter(|_| !video.info.video_details.is_live_content) {
+            self.caches.resolver.insert(key, video.clone());
+        }
+
+        Ok(video)
+    }
+
+    pub async fn new(
+        query: &str,
+        caches: SourceCaches,
+        network: Arc<Network>,
+        breaker: CircuitBreaker,
+    ) -> Result<Self> {
+        if breaker.is_open() {
+            return Err(Error::Unavailable);
+        }
+
+        let mut start = None;
+
+        let tracks = match YoutubeUrl::parse(query) {
+            // videos opened from a playlist play the whole playlist
+            Some(url) if url.playlist_id.is_some() => {
+                YoutubeSource::from_playlist(&url.playlist_url().unwrap(), &network).await?
+            }
+            Some(url) => {
+                start = url.start;
+                vec![url.video_url().ok_or(Error::Other)?]
+            }
+            None if query.starts_with("http") => return Err(Error::Other),
+            None => YoutubeSource::from_query(query, &network, &breaker).await?,
         };
 
         Ok(YoutubeSource {
-            client,
+            caches,
+            network,
+            breaker,
             tracks: tracks.into_iter(),
+            start,
         })
     }
-    async fn from_query(query: &str) -> Result<Vec<String>> {
-        let yt = YouTube::new().map_err(|_err: VideoError| Error::Other)?;
+    // songs are searched on YouTube Music and played from YouTube
+    pub async fn from_music(
+        query: &str,
+        client: HttpClient,
+        caches: SourceCaches,
+        network: Arc<Network>,
+        breaker: CircuitBreaker,
+    ) -> Result<Self> {
+        let url = if YoutubeUrl::parse(query).is_some() {
+            query.to_string()
+        } else {
+            search_song(query, &client).await?
+        };
 
-        let Video(video) = yt
-            .search_one(query, SEARCH_OPTIONS)
-            .await
-            .map_err(|_err| Error::Other)?
-            .ok_or(Error::Other)?
-        else {
+        YoutubeSource::new(&url, caches, network, breaker).await
+    }
+    async fn from_query(
+        query: &str,
+        network: &Network,
+        breaker: &CircuitBreaker,
+    ) -> Result<Vec<String>> {
+        let route = network.next_route();
+        let options = network.request_options(&route);
+        let yt = YouTube::new_with_options(&options).map_err(|_err: VideoError| Error::Other)?;
+        let client = network.client(&route);
+        let page = Url::parse_with_params(SEARCH_URL, [("search_query", query)])
+            .map_err(|_err| Error::Other)?;
+
+        // the search has no retries of its own. Pages without results are checked too, as
+        // that is how the sign in page of bot checks ends up
+        let result = with_retries(|| async {
+            match yt.search_one(query, SEARCH_OPTIONS).await {
+                Ok(Some(result)) => Ok(Some(result)),
+                Ok(None) => match check_page(&client, page.as_str()).await {
+                    Failure::Fatal => Ok(None),
+                    failure => Err(failure),
+                },
+                Err(err) => Err(match Failure::of_video(&err) {
+                    Some(failure) => failure,
+                    None => check_page(&client, page.as_str()).await,
+                }),
+            }
+        })
+        .await;
+        breaker.record(
+            result
+                .as_ref()
+                .map(|_result| ())
+                .map_err(|failure| *failure),
+        );
+
+        let Video(video) = result?.ok_or(Error::Other)? else {
             return Err(Error::Other);
         };
 
         Ok(vec![video.url])
     }
-    async fn from_playlist(url: &str) -> Result<Vec<String>> {
+    async fn from_playlist(url: &str, network: &Network) -> Result<Vec<String>> {
         let args = ["-J", "-s", "--flat-playlist", url];
 
-        let mut output = Command::new("yt-dlp")
+        let output = Command::new("yt-dlp")
+            .args(network.yt_dlp_args(&network.next_route()))
             .args(args)
             .output()
             .await
@@ -149,3 +338,130 @@ impl YoutubeSource {
         Ok(tracks)
     }
 }
+
+// played from the audio cache when a format of the video is in it
+fn video_input(client: HttpClient, info: &VideoInfo, cache: Option<&AudioCache>) -> Option<Input> {
+    let video_id = &info.video_details.video_id;
+    let formats = audio_formats(info);
+
+    let cached = cache.and_then(|cache| {
+        formats
+            .iter()
+            .find_map(|format| cache.get(&cache_name(video_id, format)))
+    });
+    if let Some(path) = cached {
+        debug!(video_id, "playing cached audio");
+        return Some(FileInput::new(path).into());
+    }
+
+    let requests = formats
+        .into_iter()
+        .map(|format| {
+            let name = cache_name(video_id, &format);
+            let content_length: Option<u64> = match &format.content_length {
+                Some(len) => str::parse::<u64>(len).ok(),
+                None => None,
+            };
+
+            let request = HttpRequest {
+                client: client.clone(),
+                request: format.url,
+                headers: HeaderMap::default(),
+                content_length,
+            };
+
+            (request, name)
+        })
+        .collect::<Vec<_>>();
+
+    if requests.is_empty() {
+        return None;
+    }
+
+    let request = FallbackRequest::new(requests, cache.cloned());
+
+    Some(Input::Lazy(Box::new(request)))
+}
+
+// like "dQw4w9WgXcQ-251.webm", the extension is what the decoder goes by
+fn cache_name(video_id: &str, format: &VideoFormat) -> String {
+    let extension = match format.mime_type.split(['/', ';']).nth(1) {
+        Some("mp4") => "m4a",
+        Some(container) => container.trim(),
+        None => "audio",
+    };
+
+    format!("{video_id}-{}.{extension}", format.itag)
+}
+
+// the format rusty_ytdl chooses, then the best of opus in webm and of AAC in mp4 in case
+// its stream fails to open
+fn audio_formats(info: &VideoInfo) -> Vec<VideoFormat> {
+    let mut formats = ytdl::choose_format(&info.formats, &VIDEO_OPTIONS)
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    for (container, codec) in [("audio/webm", "opus"), ("audio/mp4", "mp4a")] {
+        let mut fallbacks = info
+            .formats
+            .iter()
+            .filter(|format| !format.is_hls && !format.has_video && format.has_audio)
+            .filter(|format| {
+                format.mime_type.starts_with(container) && format.mime_type.contains(codec)
+            })
+            .collect::<Vec<_>>();
+        fallbacks.sort_by_key(|format| Reverse(format.bitrate));
+
+        for format in fallbacks {
+            if formats.iter().all(|chosen| chosen.url != format.url) {
+                formats.push(format.clone());
+            }
+        }
+    }
+
+    formats
+}
+
+// live streams only have HLS formats with both video and audio, the one with the best
+// audio and the least video is used
+fn live_input(client: HttpClient, info: &VideoInfo) -> Option<Input> {
+    let format = info
+        .formats
+        .iter()
+        .filter(|format| format.is_hls && format.has_audio)
+        .max_by_key(|format| (format.audio_bitrate, Reverse(format.bitrate)))?;
+
+    let request = HlsRequest::new(client, format.url.clone(), "aac");
+
+    Some(Input::Lazy(Box::new(request)))
+}
+
+// chapters written in the description as lines like "1:23 Title", YouTube only shows
+// them when the first one starts at 0:00
+fn description_chapters(description: &str) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+
+    for line in description.lines() {
+        let mut words = line.split_whitespace();
+        let Some(start) = words.next().and_then(parse_timestamp) else {
+            continue;
+        };
+
+        let title = words.collect::<Vec<_>>().join(" ");
+        let title = title.trim_start_matches(['-', '|', ':']).trim();
+        chapters.push(Chapter {
+            title: title.to_string(),
+            start,
+        });
+    }
+
+    if chapters
+        .first()
+        .is_none_or(|chapter| !chapter.start.is_zero())
+    {
+        return Vec::new();
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+
+    chapters
+}
@@ -0,0 +1,281 @@
+use reqwest::Url;
+use std::time::Duration;
+
+const HOSTS: [&str; 4] = [
+    "youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtube-nocookie.com",
+];
+const SHORT_HOST: &str = "youtu.be";
+// paths that are followed by the video id
+const VIDEO_PATHS: [&str; 5] = ["shorts", "live", "embed", "v", "e"];
+
+// parts of a YouTube link that matter for playback
+#[derive(Debug, PartialEq, Eq)]
+pub struct YoutubeUrl {
+    pub video_id: Option<String>,
+    pub playlist_id: Option<String>,
+    pub start: Option<Duration>,
+}
+
+impl YoutubeUrl {
+    // None when the text isn't a link to a video or a playlist on YouTube
+    pub fn parse(text: &str) -> Option<YoutubeUrl> {
+        let text = text.trim();
+        let url = match Url::parse(text) {
+            Ok(url) => url,
+            // links are often pasted without the scheme
+            Err(_err) => Url::parse(&format!("https://{text}")).ok()?,
+        };
+
+        let host = url.host_str()?.trim_start_matches("www.");
+        let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+
+        let path_id = if host == SHORT_HOST {
+            segments.next()
+        } else if HOSTS.contains(&host) {
+            match (segments.next(), segments.next()) {
+                (Some(path), id) if VIDEO_PATHS.contains(&path) => id,
+                _ => None,
+            }
+        } else {
+            return None;
+        };
+
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.into_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        let video_id = path_id
+            .map(str::to_string)
+            .or_else(|| query("v"))
+            .filter(|id| is_id(id));
+        let playlist_id = query("list").filter(|id| is_id(id));
+        let start = query("t")
+            .or_else(|| query("start"))
+            // some links put the time in the fragment, like #t=1m30s
+            .or_else(|| url.fragment()?.strip_prefix("t=").map(str::to_string))
+            .and_then(|time| parse_time(&time));
+
+        if video_id.is_none() && playlist_id.is_none() {
+            return None;
+        }
+
+        Some(YoutubeUrl {
+            video_id,
+            playlist_id,
+            start,
+        })
+    }
+
+    pub fn video_url(&self) -> Option<String> {
+        let id = self.video_id.as_ref()?;
+
+        Some(format!("https://www.youtube.com/watch?v={id}"))
+    }
+
+    pub fn playlist_url(&self) -> Option<String> {
+        let id = self.playlist_id.as_ref()?;
+
+        Some(format!("https://www.youtube.com/playlist?list={id}"))
+    }
+}
+
+fn is_id(id: &str) -> bool {
+    id.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// "90", "90s", "1m30s" or "1h2m3s"
+fn parse_time(time: &str) -> Option<Duration> {
+    if let Ok(secs) = time.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+
+    for c in time.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        secs = secs.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO: &str = "dQw4w9WgXcQ";
+    const PLAYLIST: &str = "PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI";
+
+    fn video(start: Option<u64>) -> Option<YoutubeUrl> {
+        Some(YoutubeUrl {
+            video_id: Some(VIDEO.to_string()),
+            playlist_id: None,
+            start: start.map(Duration::from_secs),
+        })
+    }
+
+    fn video_in_playlist(start: Option<u64>) -> Option<YoutubeUrl> {
+        Some(YoutubeUrl {
+            playlist_id: Some(PLAYLIST.to_string()),
+            ..video(start)?
+        })
+    }
+
+    #[test]
+    fn watch() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn watch_without_scheme_and_www() {
+        let url = format!("youtube.com/watch?v={VIDEO}");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn mobile() {
+        let url = format!("https://m.youtube.com/watch?v={VIDEO}&feature=share");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn watch_with_playlist() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}&list={PLAYLIST}&index=2");
+        assert_eq!(YoutubeUrl::parse(&url), video_in_playlist(None));
+    }
+
+    #[test]
+    fn playlist_before_video() {
+        let url = format!("https://www.youtube.com/watch?list={PLAYLIST}&v={VIDEO}");
+        assert_eq!(YoutubeUrl::parse(&url), video_in_playlist(None));
+    }
+
+    #[test]
+    fn playlist() {
+        let url = format!("https://www.youtube.com/playlist?list={PLAYLIST}");
+        let expected = YoutubeUrl {
+            video_id: None,
+            playlist_id: Some(PLAYLIST.to_string()),
+            start: None,
+        };
+        assert_eq!(YoutubeUrl::parse(&url), Some(expected));
+    }
+
+    #[test]
+    fn short_link() {
+        let url = format!("https://youtu.be/{VIDEO}");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn short_link_with_playlist() {
+        let url = format!("https://youtu.be/{VIDEO}?list={PLAYLIST}");
+        assert_eq!(YoutubeUrl::parse(&url), video_in_playlist(None));
+    }
+
+    #[test]
+    fn short_link_with_time() {
+        let url = format!("https://youtu.be/{VIDEO}?t=42");
+        assert_eq!(YoutubeUrl::parse(&url), video(Some(42)));
+    }
+
+    #[test]
+    fn music() {
+        let url = format!("https://music.youtube.com/watch?v={VIDEO}&feature=share");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn shorts() {
+        let url = format!("https://www.youtube.com/shorts/{VIDEO}?feature=share");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn live() {
+        let url = format!("https://www.youtube.com/live/{VIDEO}?si=abc");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn embed_with_start() {
+        let url = format!("https://www.youtube-nocookie.com/embed/{VIDEO}?start=30");
+        assert_eq!(YoutubeUrl::parse(&url), video(Some(30)));
+    }
+
+    #[test]
+    fn time_with_units() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}&t=1h2m3s");
+        assert_eq!(YoutubeUrl::parse(&url), video(Some(3723)));
+    }
+
+    #[test]
+    fn time_in_seconds_with_unit() {
+        let url = format!("https://www.youtube.com/watch?t=90s&v={VIDEO}");
+        assert_eq!(YoutubeUrl::parse(&url), video(Some(90)));
+    }
+
+    #[test]
+    fn time_in_fragment() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}#t=1m30s");
+        assert_eq!(YoutubeUrl::parse(&url), video(Some(90)));
+    }
+
+    #[test]
+    fn invalid_time_is_ignored() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}&t=soon");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn overflowing_time_is_ignored() {
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}&t=9999999999999999h");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+
+        let url = format!("https://www.youtube.com/watch?v={VIDEO}&t=18446744073709551615s1s");
+        assert_eq!(YoutubeUrl::parse(&url), video(None));
+    }
+
+    #[test]
+    fn other_sites() {
+        assert_eq!(YoutubeUrl::parse("https://example.com/watch?v=abc"), None);
+        assert_eq!(
+            YoutubeUrl::parse("https://notyoutube.com/watch?v=abc"),
+            None
+        );
+    }
+
+    #[test]
+    fn search_query() {
+        assert_eq!(YoutubeUrl::parse("never gonna give you up"), None);
+    }
+
+    #[test]
+    fn channel_page() {
+        assert_eq!(YoutubeUrl::parse("https://www.youtube.com/@channel"), None);
+    }
+}
@@ -0,0 +1,57 @@
+use super::{Result, SourceError as Error};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+
+const SEARCH_URL: &str = "https://music.youtube.com/youtubei/v1/search?prettyPrint=false";
+const CLIENT_NAME: &str = "WEB_REMIX";
+const CLIENT_VERSION: &str = "1.20240101.01.00";
+// search filter that only returns songs, without videos, albums or artists
+const SONGS_FILTER: &str = "EgWKAQIIAWoKEAoQAxAEEAkQBQ==";
+
+// url of the first song found on YouTube Music
+pub async fn search_song(query: &str, client: &HttpClient) -> Result<String> {
+    let body = json!({
+        "context": {
+            "client": {
+                "clientName": CLIENT_NAME,
+                "clientVersion": CLIENT_VERSION,
+                "hl": "en",
+            }
+        },
+        "query": query,
+        "params": SONGS_FILTER,
+    });
+
+    let response = client
+        .post(SEARCH_URL)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|_err| Error::Other)?
+        .text()
+        .await
+        .map_err(|_err| Error::Other)?;
+
+    let response = serde_json::from_str::<Value>(&response).map_err(|_err| Error::Other)?;
+
+    // results are deeply nested, every song is a list item with its video id somewhere inside
+    let video_id = find_key(&response, "musicResponsiveListItemRenderer")
+        .and_then(|item| find_key(item, "videoId"))
+        .and_then(Value::as_str)
+        .ok_or(Error::Other)?;
+
+    Ok(format!("https://www.youtube.com/watch?v={video_id}"))
+}
+
+// first value of the key, searched depth first
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .or_else(|| map.values().find_map(|value| find_key(value, key))),
+        Value::Array(values) => values.iter().find_map(|value| find_key(value, key)),
+        _ => None,
+    }
+}
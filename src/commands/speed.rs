@@ -0,0 +1,21 @@
+use super::*;
+use crate::audio::{MAX_SPEED, MIN_SPEED};
+
+#[poise::command(prefix_command, guild_only, aliases("tempo"))]
+pub async fn speed(ctx: Context<'_>, speed: f32) -> Result<(), Error> {
+    if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        ctx.say(format!("Speed must be between {MIN_SPEED} and {MAX_SPEED}"))
+            .await?;
+
+        return Ok(());
+    }
+
+    ctx.data()
+        .guild(ctx.guild_id().unwrap())
+        .filters
+        .set_speed(speed);
+
+    ctx.say(format!("Speed set to **{speed}x**")).await?;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+// shows how well the caches shared by all guilds work
+#[poise::command(prefix_command)]
+pub async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+    let caches = &ctx.data().caches;
+    let stats = caches.resolver.stats();
+
+    let mut message = format!(
+        "Resolver cache: **{}** songs, **{:.0}%** hit rate ({} hits, {} misses)",
+        stats.entries,
+        stats.hit_rate() * 100.0,
+        stats.hits,
+        stats.misses
+    );
+    if let Some(audio) = &caches.audio {
+        let stats = audio.stats();
+        message += &format!(
+            "\nAudio cache: **{}** files, **{:.1}** MB",
+            stats.files,
+            stats.size as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    ctx.say(message).await?;
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::commands::sources::SearchSource;
+use crate::logging::LogFormat;
+use crate::network::{parse_cookies, Ipv6Block};
+use crate::player::{Transitions, MAX_CROSSFADE};
+use reqwest::Proxy;
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_LOUDNESS_TARGET: f32 = -14.0;
+const DEFAULT_LYRICS_URL: &str = "https://lrclib.net";
+const DEFAULT_DATABASE_PATH: &str = "crusty.json";
+// only warnings of the libraries, they log a lot
+const DEFAULT_LOG_LEVEL: &str = "warn,bot_rust=info";
+const DEFAULT_RESOLVER_CACHE_SIZE: usize = 256;
+// youtube stream urls stop working after about 6 hours
+const DEFAULT_RESOLVER_CACHE_TTL: u64 = 30 * 60;
+const DEFAULT_AUDIO_CACHE_SIZE_MB: u64 = 1024;
+
+// bot settings read from environment variables
+pub struct Config {
+    // target loudness in LUFS, None when normalization is turned off
+    pub loudness_target: Option<f32>,
+    // defaults for every guild, can be changed with commands
+    pub transitions: Transitions,
+    // base url of the LRCLIB compatible lyrics API
+    pub lyrics_url: String,
+    // file of the local database with saved playlists
+    pub database_path: PathBuf,
+    // where queries without a prefix are searched, can be changed per guild
+    pub default_source: SearchSource,
+    // directory local files are played from, they are disabled without it
+    pub local_dir: Option<PathBuf>,
+    // base url of the SponsorBlock compatible API, segments aren't skipped without it
+    pub sponsorblock_url: Option<String>,
+    // how many resolved songs are kept and for how long, 0 turns the cache off
+    pub resolver_cache_size: usize,
+    pub resolver_cache_ttl: Duration,
+    // directory audio of played YouTube videos is kept in and its size limit in bytes,
+    // nothing is kept without it
+    pub audio_cache_dir: Option<PathBuf>,
+    pub audio_cache_size: u64,
+    // shows the current track in the bot's activity and the voice channel status
+    pub now_playing_status: bool,
+    // address of the /healthz and /metrics server, it isn't started without it
+    pub metrics_addr: Option<SocketAddr>,
+    // proxies YouTube is used through in turns, with their urls
+    pub youtube_proxies: Vec<(String, Proxy)>,
+    // "name=value; ..." for age restricted videos, the file too if it is a cookies.txt
+    pub youtube_cookies: Option<String>,
+    pub youtube_cookie_file: Option<PathBuf>,
+    // every video is resolved and streamed from a random address of the block
+    pub youtube_ipv6_block: Option<Ipv6Block>,
+    // address of the API for dashboards and the token it needs, it isn't started without it
+    pub api_addr: Option<SocketAddr>,
+    pub api_token: Option<String>,
+    // filter directives like RUST_LOG has them
+    pub log_level: String,
+    pub log_format: LogFormat,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let loudness_target = match env::var("LOUDNESS_TARGET").as_deref() {
+            Ok("off") => None,
+            _ => Some(parse_var("LOUDNESS_TARGET").unwrap_or(DEFAULT_LOUDNESS_TARGET)),
+        };
+
+        let crossfade = parse_var("CROSSFADE_SECS").unwrap_or(0.0);
+        let transitions = Transitions {
+            crossfade: Duration::from_secs_f32(crossfade).min(MAX_CROSSFADE),
+            gapless: parse_var("GAPLESS").unwrap_or(true),
+        };
+
+        let lyrics_url = env::var("LYRICS_URL").unwrap_or_else(|_| DEFAULT_LYRICS_URL.to_string());
+
+        let database_path = parse_var("DATABASE_PATH").unwrap_or(DEFAULT_DATABASE_PATH.into());
+
+        let youtube_proxies = env::var("YOUTUBE_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| match Proxy::all(url) {
+                Ok(proxy) => (url.to_string(), proxy),
+                Err(_) => panic!("invalid value of YOUTUBE_PROXIES: {url}"),
+            })
+            .collect();
+
+        let (youtube_cookies, youtube_cookie_file) =
+            match parse_var::<PathBuf>("YOUTUBE_COOKIE_FILE") {
+                Some(path) => {
+                    let text = fs::read_to_string(&path)
+                        .unwrap_or_else(|err| panic!("error reading YOUTUBE_COOKIE_FILE: {err}"));
+                    let (cookies, netscape) = parse_cookies(&text);
+
+                    (Some(cookies), netscape.then_some(path))
+                }
+                None => (None, None),
+            };
+
+        Config {
+            loudness_target,
+            transitions,
+            lyrics_url,
+            database_path,
+            default_source: parse_var("DEFAULT_SOURCE").unwrap_or_default(),
+            local_dir: parse_var("LOCAL_MUSIC_DIR"),
+            sponsorblock_url: parse_var("SPONSORBLOCK_URL"),
+            resolver_cache_size: parse_var("RESOLVER_CACHE_SIZE")
+                .unwrap_or(DEFAULT_RESOLVER_CACHE_SIZE),
+            resolver_cache_ttl: Duration::from_secs(
+                parse_var("RESOLVER_CACHE_TTL_SECS").unwrap_or(DEFAULT_RESOLVER_CACHE_TTL),
+            ),
+            audio_cache_dir: parse_var("AUDIO_CACHE_DIR"),
+            audio_cache_size: parse_var("AUDIO_CACHE_SIZE_MB")
+                .unwrap_or(DEFAULT_AUDIO_CACHE_SIZE_MB)
+                .saturating_mul(1024 * 1024),
+            now_playing_status: parse_var("NOW_PLAYING_STATUS").unwrap_or(false),
+            metrics_addr: parse_var("METRICS_ADDR"),
+            youtube_proxies,
+            youtube_cookies,
+            youtube_cookie_file,
+            youtube_ipv6_block: parse_var("YOUTUBE_IPV6_BLOCK"),
+            api_addr: parse_var("API_ADDR"),
+            api_token: parse_var("API_TOKEN"),
+            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| DEFAULT_LOG_LEVEL.to_string()),
+            log_format: parse_var("LOG_FORMAT").unwrap_or_default(),
+        }
+    }
+}
+
+fn parse_var<T: FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => panic!("invalid value of {name}: {value}"),
+    }
+}
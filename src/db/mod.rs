@@ -0,0 +1,70 @@
+mod playlists;
+
+pub use playlists::{SavedPlaylist, SavedTrack};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{ErrorKind, Result as IoResult};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+// everything the bot stores between restarts
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Tables {
+    #[serde(default)]
+    pub playlists: Vec<SavedPlaylist>,
+}
+
+// local database kept in a json file, written after every change
+pub struct Database {
+    path: PathBuf,
+    tables: RwLock<Tables>,
+    // held while a change is written, so changes made at the same time don't undo each other
+    writing: Mutex<()>,
+}
+
+impl Database {
+    pub fn open(path: PathBuf) -> IoResult<Self> {
+        let tables = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Tables::default(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Database {
+            path,
+            tables: RwLock::new(tables),
+            writing: Mutex::new(()),
+        })
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&Tables) -> T) -> T {
+        f(&self.tables.read().unwrap())
+    }
+
+    // the change is made to a copy of the tables, which replaces them once it's on disk. The
+    // closure returns None when it changed nothing, the file isn't written then
+    pub async fn write<T>(&self, f: impl FnOnce(&mut Tables) -> Option<T>) -> IoResult<Option<T>> {
+        let _writing = self.writing.lock().await;
+
+        let mut tables = self.read(Tables::clone);
+        let Some(result) = f(&mut tables) else {
+            return Ok(None);
+        };
+
+        // written next to the file and renamed over it, so a crash can't leave it half written
+        let path = self.path.clone();
+        let tables = tokio::task::spawn_blocking(move || {
+            let temp = path.with_extension("tmp");
+            fs::write(&temp, serde_json::to_vec(&tables)?)?;
+            fs::rename(&temp, &path)?;
+
+            IoResult::Ok(tables)
+        })
+        .await??;
+        *self.tables.write().unwrap() = tables;
+
+        Ok(Some(result))
+    }
+}
@@ -0,0 +1,74 @@
+use super::Tables;
+use poise::serenity_prelude::{GuildId, UserId};
+use serde::{Deserialize, Serialize};
+
+// playlist of a user in a guild, shared ones can be loaded by everyone in it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedPlaylist {
+    pub guild: GuildId,
+    pub owner: UserId,
+    pub name: String,
+    pub shared: bool,
+    pub tracks: Vec<SavedTrack>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedTrack {
+    pub title: String,
+    pub url: String,
+}
+
+impl Tables {
+    // playlists the user can load, their own first
+    pub fn visible_playlists(
+        &self,
+        guild: GuildId,
+        user: UserId,
+    ) -> impl Iterator<Item = &SavedPlaylist> {
+        let own = self
+            .playlists
+            .iter()
+            .filter(move |playlist| playlist.guild == guild && playlist.owner == user);
+        let shared = self.playlists.iter().filter(move |playlist| {
+            playlist.guild == guild && playlist.owner != user && playlist.shared
+        });
+
+        own.chain(shared)
+    }
+
+    // names are compared case insensitively
+    pub fn find_playlist(
+        &self,
+        guild: GuildId,
+        user: UserId,
+        name: &str,
+    ) -> Option<&SavedPlaylist> {
+        self.visible_playlists(guild, user)
+            .find(|playlist| playlist.name.eq_ignore_ascii_case(name))
+    }
+
+    // only the owner can change a playlist
+    pub fn own_playlist_mut(
+        &mut self,
+        guild: GuildId,
+        user: UserId,
+        name: &str,
+    ) -> Option<&mut SavedPlaylist> {
+        self.playlists.iter_mut().find(|playlist| {
+            playlist.guild == guild
+                && playlist.owner == user
+                && playlist.name.eq_ignore_ascii_case(name)
+        })
+    }
+
+    pub fn remove_playlist(&mut self, guild: GuildId, user: UserId, name: &str) -> bool {
+        let len = self.playlists.len();
+        self.playlists.retain(|playlist| {
+            !(playlist.guild == guild
+                && playlist.owner == user
+                && playlist.name.eq_ignore_ascii_case(name))
+        });
+
+        self.playlists.len() != len
+    }
+}
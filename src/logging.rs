@@ -0,0 +1,88 @@
+use crate::config::Config;
+use poise::serenity_prelude as serenity;
+use serenity::{async_trait, Client, FullEvent, Interaction};
+use std::str::FromStr;
+use tracing::field::Empty;
+use tracing::{info_span, Instrument, Span};
+use tracing_subscriber::EnvFilter;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // one json object per line, for log aggregation
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+// logs of the bot and its libraries go to stdout
+pub fn init(config: &Config) {
+    let filter = EnvFilter::try_new(&config.log_level)
+        .unwrap_or_else(|_| panic!("invalid value of LOG_LEVEL: {}", config.log_level));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match config.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+// runs poise in a span for every message and pressed button, the command is recorded
+// once poise found it
+pub struct TracedFramework<F>(pub F);
+
+#[async_trait]
+impl<F: serenity::Framework> serenity::Framework for TracedFramework<F> {
+    async fn init(&mut self, client: &Client) {
+        self.0.init(client).await;
+    }
+
+    async fn dispatch(&self, ctx: serenity::Context, event: FullEvent) {
+        let span = event_span(&event);
+
+        self.0.dispatch(ctx, event).instrument(span).await;
+    }
+}
+
+fn event_span(event: &FullEvent) -> Span {
+    let (span, guild) = match event {
+        FullEvent::Message { new_message } => (
+            info_span!(
+                "command",
+                guild = Empty,
+                user = new_message.author.id.get(),
+                command = Empty,
+            ),
+            new_message.guild_id,
+        ),
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } => (
+            info_span!(
+                "component",
+                guild = Empty,
+                user = interaction.user.id.get(),
+                id = interaction.data.custom_id,
+            ),
+            interaction.guild_id,
+        ),
+        _ => return Span::none(),
+    };
+
+    if let Some(guild) = guild {
+        span.record("guild", guild.get());
+    }
+
+    span
+}
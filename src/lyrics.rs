@@ -0,0 +1,142 @@
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use std::time::Duration;
+
+// discord limits embed descriptions to 4096 characters
+const PAGE_LEN: usize = 2000;
+
+pub enum Lyrics {
+    // lines with the time they start at, sorted by it
+    Synced(Vec<(Duration, String)>),
+    Plain(String),
+}
+
+impl Lyrics {
+    // lrc lyrics are synced, anything else is shown as it is
+    pub fn parse(text: &str) -> Option<Lyrics> {
+        let mut lines = text.lines().flat_map(parse_lrc_line).collect::<Vec<_>>();
+
+        if !lines.is_empty() {
+            lines.sort_by_key(|(time, _)| *time);
+
+            return Some(Lyrics::Synced(lines));
+        }
+
+        let text = text.trim();
+        (!text.is_empty()).then(|| Lyrics::Plain(text.to_string()))
+    }
+
+    // splits lyrics into pages at line boundaries
+    pub fn pages(&self) -> Vec<String> {
+        let lines: Vec<&str> = match self {
+            Lyrics::Synced(lines) => lines.iter().map(|(_, line)| line.as_str()).collect(),
+            Lyrics::Plain(text) => text.lines().collect(),
+        };
+
+        let mut pages = vec![String::new()];
+        for line in lines {
+            let page = pages.last_mut().unwrap();
+            if !page.is_empty() && page.len() + line.len() > PAGE_LEN {
+                pages.push(String::new());
+            }
+
+            let page = pages.last_mut().unwrap();
+            page.push_str(line);
+            page.push('\n');
+        }
+
+        pages
+    }
+}
+
+// "[mm:ss.xx]text", a line can have more than one timestamp when it is repeated
+fn parse_lrc_line(line: &str) -> Vec<(Duration, String)> {
+    let mut times = Vec::new();
+    let mut rest = line.trim();
+
+    while let Some((tag, after)) = rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+        // tags like [ar:artist] are not timestamps
+        let Some(time) = parse_timestamp(tag) else {
+            break;
+        };
+
+        times.push(time);
+        rest = after;
+    }
+
+    times
+        .into_iter()
+        .map(|time| (time, rest.trim().to_string()))
+        .collect()
+}
+
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (mins, secs) = tag.split_once(':')?;
+    let mins = mins.parse::<u64>().ok()?;
+    // negative, infinite or NaN seconds come from broken files
+    let secs = Duration::try_from_secs_f64(secs.parse::<f64>().ok()?).ok()?;
+
+    Duration::from_secs(mins.checked_mul(60)?).checked_add(secs)
+}
+
+// HTTP API compatible with LRCLIB, so a local server can be used in its place
+pub struct LyricsProvider {
+    client: HttpClient,
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderTrack {
+    synced_lyrics: Option<String>,
+    plain_lyrics: Option<String>,
+}
+
+impl LyricsProvider {
+    pub fn new(client: HttpClient, url: String) -> Self {
+        LyricsProvider { client, url }
+    }
+
+    pub async fn search(&self, title: &str) -> Result<Option<Lyrics>, reqwest::Error> {
+        let response = self
+            .client
+            .get(format!("{}/api/search", self.url.trim_end_matches('/')))
+            .query(&[("q", clean_title(title))])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let tracks = serde_json::from_str::<Vec<ProviderTrack>>(&response).unwrap_or_default();
+
+        // first result that has synced lyrics, otherwise the first with any
+        let lyrics = tracks
+            .iter()
+            .find_map(|track| track.synced_lyrics.as_deref())
+            .or_else(|| {
+                tracks
+                    .iter()
+                    .find_map(|track| track.plain_lyrics.as_deref())
+            });
+
+        Ok(lyrics.and_then(Lyrics::parse))
+    }
+}
+
+// youtube titles often have parts like "(Official Video)" that break the search
+fn clean_title(title: &str) -> String {
+    let mut clean = String::new();
+    let mut depth = 0;
+
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = (depth - 1).max(0),
+            c if depth == 0 => clean.push(c),
+            _ => {}
+        }
+    }
+
+    clean.split_whitespace().collect::<Vec<_>>().join(" ")
+}
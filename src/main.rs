@@ -1,10 +1,25 @@
+mod api;
+mod audio;
 mod commands;
+mod config;
+mod db;
+mod logging;
+mod lru;
+mod lyrics;
+mod metrics;
+mod network;
+mod player;
+mod sponsorblock;
+mod state;
 
 use commands::*;
+use config::Config;
+use logging::TracedFramework;
 use poise::{serenity_prelude as serenity, Framework, PrefixFrameworkOptions};
 use reqwest::Client as HttpClient;
 use serenity::prelude::{Client, TypeMapKey};
 use songbird::SerenityInit;
+use std::sync::Arc;
 struct HttpKey;
 
 impl TypeMapKey for HttpKey {
@@ -13,18 +28,76 @@ impl TypeMapKey for HttpKey {
 
 #[tokio::main]
 async fn main() {
-    let framework = create_framework();
+    let config = Config::from_env();
+    logging::init(&config);
 
-    let mut client = create_client(framework).await;
+    let data = Arc::new(Data::new(config).expect("error opening database"));
+    let (metrics, metrics_addr) = (data.metrics.clone(), data.config.metrics_addr);
+    let api = data.config.api_addr.map(|addr| {
+        let token = data.config.api_token.clone();
+        let token = token.expect("API_TOKEN is needed for the API");
+        // an empty token would let requests without one in
+        assert!(
+            token.len() >= api::MIN_TOKEN_LEN,
+            "API_TOKEN has to be at least {} characters long",
+            api::MIN_TOKEN_LEN
+        );
+
+        (addr, token)
+    });
+    let api_data = data.clone();
+    let http_client = data.network.base_client();
+    let framework = create_framework(data);
+
+    let mut client = create_client(framework, http_client).await;
+
+    if let Some(addr) = metrics_addr {
+        let bot = metrics::BotHandles {
+            shard_manager: client.shard_manager.clone(),
+            data: client.data.clone(),
+        };
+        metrics::spawn_server(addr, metrics, bot);
+    }
+    if let Some((addr, token)) = api {
+        api::spawn_server(addr, token, api_data);
+    }
 
     client.start().await.unwrap();
 }
 
-fn create_framework() -> Framework<Data, Error> {
+fn create_framework(data: Arc<Data>) -> Framework<Arc<Data>, Error> {
     poise::Framework::builder()
-        .setup(|_, _, _| Box::pin(async move { Ok(()) }))
+        .setup(|_, _, _| Box::pin(async move { Ok(data) }))
         .options(poise::FrameworkOptions {
-            commands: vec![join(), play(), skip(), seek()],
+            commands: vec![
+                join(),
+                play(),
+                skip(),
+                seek(),
+                filter(),
+                speed(),
+                pitch(),
+                crossfade(),
+                gapless(),
+                lyrics(),
+                history(),
+                previous(),
+                playlist(),
+                queue(),
+                autoplay(),
+                source(),
+                chapter(),
+                stats(),
+            ],
+            event_handler: |ctx, event, _framework, data| Box::pin(handle_event(ctx, event, data)),
+            pre_command: |ctx| {
+                Box::pin(async move {
+                    let command = ctx.command().qualified_name.as_str();
+                    tracing::Span::current().record("command", command);
+                    tracing::info!("command invoked");
+                })
+            },
+            on_error: |error| Box::pin(on_error(error)),
             prefix_options: PrefixFrameworkOptions {
                 prefix: Some(">".into()),
                 case_insensitive_commands: true,
@@ -35,15 +108,52 @@ fn create_framework() -> Framework<Data, Error> {
         .build()
 }
 
-async fn create_client(framework: Framework<Data, Error>) -> Client {
+async fn handle_event(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    data: &Data,
+) -> Result<(), Error> {
+    if let serenity::FullEvent::Ready { .. } = event {
+        let _ = data.discord.set(ctx.clone());
+    }
+
+    if let serenity::FullEvent::InteractionCreate {
+        interaction: serenity::Interaction::Component(interaction),
+    } = event
+    {
+        handle_component(ctx, data, interaction).await?;
+    }
+
+    Ok(())
+}
+
+// command errors are logged with their span instead of printed, the member still sees them
+async fn on_error(error: poise::FrameworkError<'_, Arc<Data>, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx, .. } => {
+            tracing::error!(error = %error, "command failed");
+
+            if let Err(err) = ctx.say(error.to_string()).await {
+                tracing::warn!(error = %err, "error message not sent");
+            }
+        }
+        error => {
+            if let Err(err) = poise::builtins::on_error(error).await {
+                tracing::warn!(error = %err, "error message not sent");
+            }
+        }
+    }
+}
+
+async fn create_client(framework: Framework<Arc<Data>, Error>, http_client: HttpClient) -> Client {
     let token = std::env::var("DISCORD_TOKEN").expect("missing token");
     let intents =
         serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
 
     serenity::ClientBuilder::new(token, intents)
-        .framework(framework)
+        .framework(TracedFramework(framework))
         .register_songbird()
-        .type_map_insert::<HttpKey>(HttpClient::new())
+        .type_map_insert::<HttpKey>(http_client)
         .await
         .expect("error creating client")
 }
@@ -0,0 +1,196 @@
+mod server;
+
+pub use server::{spawn_server, BotHandles};
+
+use crate::commands::sources::SourceError;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// upper bounds of the source resolution latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+// what the bot did since it started, in the Prometheus text format. The state of the
+// voice sessions and shards is read when the metrics are scraped
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    tracks_played: AtomicU64,
+    decode_errors: AtomicU64,
+    // by the name of the SourceError variant
+    source_errors: Mutex<BTreeMap<&'static str, u64>>,
+    resolution: Mutex<Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    // not cumulative, the last one counts what didn't fit in any bucket
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+// current state of the bot, gathered by the server for every scrape
+pub struct Snapshot {
+    // length of the queue of every guild the bot is connected in
+    pub sessions: Vec<(u64, usize)>,
+    // heartbeat latency of every shard that measured it
+    pub gateway_latency: Vec<(u32, Duration)>,
+}
+
+impl Metrics {
+    // tracks that ended, were skipped or failed
+    pub fn track_played(&self) {
+        self.0.tracks_played.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // tracks that stopped because their input couldn't be decoded
+    pub fn decode_error(&self) {
+        self.0.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // a source looked a track up, failed ones count as errors too
+    pub fn resolved(&self, latency: Duration, error: Option<&SourceError>) {
+        let secs = latency.as_secs_f64();
+
+        {
+            let mut histogram = self.0.resolution.lock().unwrap();
+            let bucket = LATENCY_BUCKETS
+                .iter()
+                .position(|bound| secs <= *bound)
+                .unwrap_or(LATENCY_BUCKETS.len());
+
+            histogram.buckets[bucket] += 1;
+            histogram.sum += secs;
+            histogram.count += 1;
+        }
+
+        if let Some(error) = error {
+            *self
+                .0
+                .source_errors
+                .lock()
+                .unwrap()
+                .entry(error.name())
+                .or_default() += 1;
+        }
+    }
+
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "crusty_voice_sessions",
+            "gauge",
+            "Voice channels the bot is in",
+        );
+        let _ = writeln!(out, "crusty_voice_sessions {}", snapshot.sessions.len());
+
+        header(
+            &mut out,
+            "crusty_queue_length",
+            "gauge",
+            "Tracks in the queue of a guild",
+        );
+        for (guild, len) in &snapshot.sessions {
+            let _ = writeln!(out, "crusty_queue_length{{guild=\"{guild}\"}} {len}");
+        }
+
+        header(
+            &mut out,
+            "crusty_tracks_played_total",
+            "counter",
+            "Tracks that were played",
+        );
+        let _ = writeln!(
+            out,
+            "crusty_tracks_played_total {}",
+            self.0.tracks_played.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "crusty_source_resolution_seconds",
+            "histogram",
+            "Time sources took to look a track up",
+        );
+        {
+            let histogram = self.0.resolution.lock().unwrap();
+            let mut cumulative = 0;
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "crusty_source_resolution_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "crusty_source_resolution_seconds_bucket{{le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "crusty_source_resolution_seconds_sum {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "crusty_source_resolution_seconds_count {}",
+                histogram.count
+            );
+        }
+
+        header(
+            &mut out,
+            "crusty_source_errors_total",
+            "counter",
+            "Tracks sources failed to look up",
+        );
+        for (variant, count) in self.0.source_errors.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "crusty_source_errors_total{{variant=\"{variant}\"}} {count}"
+            );
+        }
+
+        header(
+            &mut out,
+            "crusty_decode_errors_total",
+            "counter",
+            "Tracks stopped by errors of their input",
+        );
+        let _ = writeln!(
+            out,
+            "crusty_decode_errors_total {}",
+            self.0.decode_errors.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "crusty_gateway_latency_seconds",
+            "gauge",
+            "Heartbeat latency of a shard",
+        );
+        for (shard, latency) in &snapshot.gateway_latency {
+            let _ = writeln!(
+                out,
+                "crusty_gateway_latency_seconds{{shard=\"{shard}\"}} {}",
+                latency.as_secs_f64()
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
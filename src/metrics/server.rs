@@ -0,0 +1,125 @@
+use super::{Metrics, Snapshot};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use poise::serenity_prelude as serenity;
+use serenity::prelude::{RwLock, TypeMap};
+use serenity::{ConnectionStage, ShardManager};
+use songbird::serenity::SongbirdKey;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// parts of the client the endpoints look at
+#[derive(Clone)]
+pub struct BotHandles {
+    pub shard_manager: Arc<ShardManager>,
+    pub data: Arc<RwLock<TypeMap>>,
+}
+
+impl BotHandles {
+    // every shard is connected and songbird is registered
+    async fn health(&self) -> Result<(), &'static str> {
+        let runners = self.shard_manager.runners.lock().await;
+        let connected = !runners.is_empty()
+            && runners
+                .values()
+                .all(|runner| runner.stage == ConnectionStage::Connected);
+        drop(runners);
+
+        if !connected {
+            return Err("gateway not connected");
+        }
+        if !self.data.read().await.contains_key::<SongbirdKey>() {
+            return Err("songbird not registered");
+        }
+
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Snapshot {
+        let gateway_latency = self
+            .shard_manager
+            .runners
+            .lock()
+            .await
+            .iter()
+            .filter_map(|(id, runner)| Some((id.0, runner.latency?)))
+            .collect();
+
+        let songbird = self.data.read().await.get::<SongbirdKey>().cloned();
+        // collected first, so the calls aren't locked while the manager is
+        let calls = match songbird {
+            Some(songbird) => songbird.iter().collect(),
+            None => Vec::new(),
+        };
+
+        let mut sessions = Vec::new();
+        for (guild, call) in calls {
+            let call = call.lock().await;
+
+            if call.current_channel().is_some() {
+                sessions.push((guild.0.get(), call.queue().len()));
+            }
+        }
+
+        Snapshot {
+            sessions,
+            gateway_latency,
+        }
+    }
+}
+
+// serves /healthz and /metrics on the address until the bot stops
+pub fn spawn_server(addr: SocketAddr, metrics: Metrics, bot: BotHandles) {
+    let make_service = make_service_fn(move |_conn| {
+        let (metrics, bot) = (metrics.clone(), bot.clone());
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let (metrics, bot) = (metrics.clone(), bot.clone());
+
+                async move { Ok::<_, Infallible>(respond(request, &metrics, &bot).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .expect("error binding metrics server")
+        .serve(make_service);
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            tracing::error!(error = %err, "metrics server stopped");
+        }
+    });
+}
+
+async fn respond(request: Request<Body>, metrics: &Metrics, bot: &BotHandles) -> Response<Body> {
+    if request.method() != Method::GET {
+        return text(StatusCode::METHOD_NOT_ALLOWED, "");
+    }
+
+    match request.uri().path() {
+        "/healthz" => match bot.health().await {
+            Ok(()) => text(StatusCode::OK, "ok"),
+            Err(reason) => text(StatusCode::SERVICE_UNAVAILABLE, reason),
+        },
+        "/metrics" => {
+            let snapshot = bot.snapshot().await;
+
+            Response::builder()
+                .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(Body::from(metrics.render(&snapshot)))
+                .unwrap()
+        }
+        _ => text(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn text(status: StatusCode, body: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(body))
+        .unwrap()
+}
@@ -0,0 +1,198 @@
+use crate::config::Config;
+use reqwest::cookie::Jar;
+use reqwest::{Client as HttpClient, Proxy, Url};
+use rusty_ytdl::RequestOptions;
+use std::net::{IpAddr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const YOUTUBE_URL: &str = "https://www.youtube.com";
+
+// how requests to YouTube leave the bot: through which proxy, from which address and
+// with which cookies. Proxies take turns and every video gets a new address of the block
+pub struct Network {
+    // urls are kept for yt-dlp
+    proxies: Vec<(String, Proxy)>,
+    next_proxy: AtomicUsize,
+    // "name=value; ..." sent to YouTube
+    cookies: Option<String>,
+    // yt-dlp only reads cookies.txt files
+    cookie_file: Option<String>,
+    ipv6_block: Option<Ipv6Block>,
+    // route of the client shared by everything else, like the one in HttpKey
+    base: Route,
+    client: HttpClient,
+}
+
+// a proxy and source address, stream urls of a video only work from the route that
+// resolved it
+#[derive(Clone, PartialEq, Eq)]
+pub struct Route {
+    proxy: Option<usize>,
+    address: Option<Ipv6Addr>,
+}
+
+impl Network {
+    pub fn new(config: &Config) -> Self {
+        let mut network = Network {
+            proxies: config.youtube_proxies.clone(),
+            next_proxy: AtomicUsize::new(0),
+            cookies: config.youtube_cookies.clone(),
+            cookie_file: config
+                .youtube_cookie_file
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            ipv6_block: config.youtube_ipv6_block,
+            base: Route {
+                proxy: None,
+                address: None,
+            },
+            client: HttpClient::new(),
+        };
+
+        network.base = network.next_route();
+        network.client = network.build_client(&network.base);
+
+        network
+    }
+
+    pub fn next_route(&self) -> Route {
+        let proxy = match self.proxies.len() {
+            0 => None,
+            len => Some(self.next_proxy.fetch_add(1, Ordering::Relaxed) % len),
+        };
+
+        Route {
+            proxy,
+            address: self.ipv6_block.map(|block| block.random_address()),
+        }
+    }
+
+    // the client shared by sources and everything that doesn't need a route of its own
+    pub fn base_client(&self) -> HttpClient {
+        self.client.clone()
+    }
+
+    pub fn client(&self, route: &Route) -> HttpClient {
+        if *route == self.base {
+            return self.client.clone();
+        }
+
+        self.build_client(route)
+    }
+
+    fn build_client(&self, route: &Route) -> HttpClient {
+        let mut builder = HttpClient::builder();
+
+        if let Some(proxy) = route.proxy {
+            builder = builder.proxy(self.proxies[proxy].1.clone());
+        }
+        if let Some(address) = route.address {
+            builder = builder.local_address(IpAddr::V6(address));
+        }
+        // the jar only sends them to YouTube
+        if let Some(cookies) = &self.cookies {
+            let jar = Jar::default();
+            let url = Url::parse(YOUTUBE_URL).unwrap();
+            for cookie in cookies.split(';') {
+                jar.add_cookie_str(cookie.trim(), &url);
+            }
+
+            builder = builder.cookie_provider(Arc::new(jar));
+        }
+
+        builder.build().expect("error creating http client")
+    }
+
+    // the same route for rusty_ytdl, the address is its own block of one
+    pub fn request_options(&self, route: &Route) -> RequestOptions {
+        RequestOptions {
+            proxy: route.proxy.map(|proxy| self.proxies[proxy].1.clone()),
+            cookies: self.cookies.clone(),
+            ipv6_block: route.address.map(|address| format!("{address}/128")),
+        }
+    }
+
+    // and for yt-dlp
+    pub fn yt_dlp_args(&self, route: &Route) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(proxy) = route.proxy {
+            args.extend(["--proxy".to_string(), self.proxies[proxy].0.clone()]);
+        }
+        if let Some(address) = route.address {
+            args.extend(["--source-address".to_string(), address.to_string()]);
+        }
+        if let Some(file) = &self.cookie_file {
+            args.extend(["--cookies".to_string(), file.clone()]);
+        }
+
+        args
+    }
+}
+
+// like "2001:db8::/48", every address in it has to be routed to the bot's host
+#[derive(Clone, Copy)]
+pub struct Ipv6Block {
+    base: Ipv6Addr,
+    prefix: u8,
+}
+
+impl Ipv6Block {
+    fn random_address(&self) -> Ipv6Addr {
+        let mask = u128::MAX
+            .checked_shl(128 - u32::from(self.prefix))
+            .unwrap_or(0);
+        let random: u128 = rand::random();
+
+        Ipv6Addr::from((u128::from(self.base) & mask) | (random & !mask))
+    }
+}
+
+impl FromStr for Ipv6Block {
+    type Err = ();
+
+    fn from_str(block: &str) -> Result<Self, Self::Err> {
+        let (base, prefix) = block.split_once('/').ok_or(())?;
+        let base = base.parse().map_err(|_err| ())?;
+        let prefix = prefix
+            .parse()
+            .ok()
+            .filter(|prefix| *prefix <= 128)
+            .ok_or(())?;
+
+        Ok(Ipv6Block { base, prefix })
+    }
+}
+
+// cookies.txt files exported from browsers, or a "name=value; ..." header. The first
+// is also given to yt-dlp
+pub fn parse_cookies(text: &str) -> (String, bool) {
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| !line.starts_with('#') || line.starts_with("#HttpOnly_"))
+        .collect::<Vec<_>>();
+
+    // domain, subdomains, path, secure, expiry, name and value
+    let netscape = !lines.is_empty() && lines.iter().all(|line| line.split('\t').count() == 7);
+    if !netscape {
+        return (lines.join(" "), false);
+    }
+
+    let cookies = lines
+        .iter()
+        .filter_map(|line| {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let domain = fields[0].trim_start_matches("#HttpOnly_");
+
+            domain
+                .ends_with("youtube.com")
+                .then(|| format!("{}={}", fields[5], fields[6]))
+        })
+        .collect::<Vec<_>>();
+
+    (cookies.join("; "), true)
+}
@@ -0,0 +1,85 @@
+use crate::commands::sources::JsonDuration;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+// events listeners fall this far behind before they miss some
+const EVENT_BUFFER: usize = 256;
+
+// what happened to the player of a guild, sent to the API's websocket as json. Ids are
+// strings because javascript numbers can't hold them
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayerEvent {
+    TrackQueued {
+        guild: String,
+        title: String,
+        url: String,
+    },
+    TrackStarted {
+        guild: String,
+        title: String,
+        url: String,
+        duration: JsonDuration,
+    },
+    TrackEnded {
+        guild: String,
+        title: String,
+        url: String,
+        // false when it was skipped or failed
+        completed: bool,
+    },
+    Paused {
+        guild: String,
+    },
+    Resumed {
+        guild: String,
+    },
+    Seeked {
+        guild: String,
+        position: f64,
+    },
+    VolumeChanged {
+        guild: String,
+        volume: f32,
+    },
+    // tracks were skipped, moved, shuffled or removed
+    QueueChanged {
+        guild: String,
+    },
+}
+
+impl PlayerEvent {
+    pub fn guild(&self) -> &str {
+        match self {
+            PlayerEvent::TrackQueued { guild, .. }
+            | PlayerEvent::TrackStarted { guild, .. }
+            | PlayerEvent::TrackEnded { guild, .. }
+            | PlayerEvent::Paused { guild }
+            | PlayerEvent::Resumed { guild }
+            | PlayerEvent::Seeked { guild, .. }
+            | PlayerEvent::VolumeChanged { guild, .. }
+            | PlayerEvent::QueueChanged { guild } => guild,
+        }
+    }
+}
+
+// events of every guild, anyone can listen to them
+#[derive(Clone)]
+pub struct PlayerEvents(broadcast::Sender<PlayerEvent>);
+
+impl Default for PlayerEvents {
+    fn default() -> Self {
+        PlayerEvents(broadcast::channel(EVENT_BUFFER).0)
+    }
+}
+
+impl PlayerEvents {
+    pub fn send(&self, event: PlayerEvent) {
+        // nobody listening isn't an error
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PlayerEvent> {
+        self.0.subscribe()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::commands::sources::Metadata;
+use poise::serenity_prelude::UserId;
+use songbird::tracks::TrackHandle;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// how many played tracks are remembered per guild
+const HISTORY_LEN: usize = 50;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    Completed,
+    Skipped,
+}
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub metadata: Metadata,
+    pub requester: UserId,
+    // unix time the track started playing at
+    pub started: u64,
+    pub outcome: Outcome,
+    track: TrackHandle,
+}
+
+// tracks played in a guild, the newest at the back
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    // index of the entry last stepped back to, while its replay is playing
+    cursor: Option<usize>,
+}
+
+impl History {
+    // tracks are recorded once, resuming after a pause plays them again
+    pub fn record(&mut self, track: &TrackHandle, metadata: Metadata, requester: UserId) {
+        if self
+            .entries
+            .iter()
+            .any(|entry| entry.track.uuid() == track.uuid())
+        {
+            return;
+        }
+
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        // playing anything but the track stepped back to stops stepping back
+        if self
+            .cursor
+            .is_some_and(|i| self.entries[i].metadata.url != metadata.url)
+        {
+            self.cursor = None;
+        }
+
+        if self.entries.len() == HISTORY_LEN {
+            self.entries.pop_front();
+            self.cursor = self.cursor.map(|i| i.saturating_sub(1));
+        }
+        self.entries.push_back(HistoryEntry {
+            metadata,
+            requester,
+            started,
+            outcome: Outcome::Playing,
+            track: track.clone(),
+        });
+    }
+
+    pub fn finish(&mut self, track: &TrackHandle, completed: bool) {
+        let entry = self
+            .entries
+            .iter_mut()
+            .rev()
+            .find(|entry| entry.track.uuid() == track.uuid());
+
+        if let Some(entry) = entry {
+            entry.outcome = if completed {
+                Outcome::Completed
+            } else {
+                Outcome::Skipped
+            };
+        }
+    }
+
+    // newest first
+    pub fn recent(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|entry| entry.metadata.url == url)
+    }
+
+    // last track played before the current one, or before the one last stepped back to
+    pub fn previous(&self, current: Option<&TrackHandle>) -> Option<&HistoryEntry> {
+        let end = self.cursor.unwrap_or(self.entries.len());
+
+        self.entries
+            .range(..end)
+            .rev()
+            .find(|entry| current.is_none_or(|current| entry.track.uuid() != current.uuid()))
+    }
+
+    // the entry is played again, so the next `previous` goes back further
+    pub fn step_back(&mut self, to: &HistoryEntry) {
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.track.uuid() == to.track.uuid())
+        {
+            self.cursor = Some(index);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod events;
+mod history;
+mod presence;
+mod sponsor;
+mod transition;
+
+pub use events::{PlayerEvent, PlayerEvents};
+pub use history::{History, Outcome};
+pub use presence::{Presence, StatusTarget};
+pub use sponsor::SegmentSkipper;
+pub use transition::{ClipStopper, TransitionHandler, Transitions, MAX_CROSSFADE};
@@ -0,0 +1,91 @@
+use dashmap::DashMap;
+use poise::serenity_prelude as serenity;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::Client as HttpClient;
+use serenity::{ActivityData, ChannelId, GuildId, Http, ShardMessenger};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+const API_URL: &str = "https://discord.com/api/v10";
+// discord cuts activity names at 128 characters
+const MAX_ACTIVITY_LEN: usize = 128;
+const MAX_STATUS_LEN: usize = 500;
+
+// shows what is playing in the bot's activity and the status of its voice channels,
+// the activity counts the sessions when more than one guild is listening
+#[derive(Clone, Default)]
+pub struct Presence {
+    // title of the current track of every guild that is playing
+    sessions: Arc<DashMap<GuildId, String>>,
+    // its own, the one in HttpKey goes through the YouTube proxies and must not get the token
+    client: HttpClient,
+}
+
+// what a guild needs to change its status
+pub struct StatusTarget<'a> {
+    pub guild: GuildId,
+    pub voice_channel: Option<ChannelId>,
+    pub shard: &'a ShardMessenger,
+    pub http: &'a Http,
+}
+
+impl Presence {
+    pub async fn playing(&self, target: StatusTarget<'_>, title: &str) {
+        self.sessions.insert(target.guild, title.to_string());
+        self.update_activity(target.shard);
+
+        if let Some(channel) = target.voice_channel {
+            self.set_voice_status(&target, channel, title).await;
+        }
+    }
+
+    // the queue of the guild ended
+    pub async fn stopped(&self, target: StatusTarget<'_>) {
+        self.sessions.remove(&target.guild);
+        self.update_activity(target.shard);
+
+        if let Some(channel) = target.voice_channel {
+            self.set_voice_status(&target, channel, "").await;
+        }
+    }
+
+    fn update_activity(&self, shard: &ShardMessenger) {
+        let activity = match self.sessions.len() {
+            0 => None,
+            1 => self.sessions.iter().next().map(|session| {
+                ActivityData::listening(truncate(session.value(), MAX_ACTIVITY_LEN))
+            }),
+            sessions => Some(ActivityData::listening(format!(
+                "music in {sessions} servers"
+            ))),
+        };
+
+        shard.set_activity(activity);
+    }
+
+    // serenity has no endpoint for it yet, bots without the permission to set it are ignored
+    async fn set_voice_status(&self, target: &StatusTarget<'_>, channel: ChannelId, status: &str) {
+        let body = serde_json::json!({ "status": truncate(status, MAX_STATUS_LEN) });
+
+        let response = self
+            .client
+            .put(format!("{API_URL}/channels/{channel}/voice-status"))
+            .header(AUTHORIZATION, target.http.token())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if !response.status().is_success() => {
+                debug!(status = %response.status(), %channel, "voice status not set")
+            }
+            Err(err) => warn!(error = %err, %channel, "voice status not set"),
+            Ok(_) => {}
+        }
+    }
+}
+
+fn truncate(text: &str, len: usize) -> String {
+    text.chars().take(len).collect()
+}
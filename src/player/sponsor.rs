@@ -0,0 +1,98 @@
+use super::transition::TICK;
+use crate::audio::PlaybackClock;
+use crate::sponsorblock::{Segment, SponsorBlock};
+use poise::serenity_prelude as serenity;
+use serenity::async_trait;
+use songbird::events::{Event, EventContext, EventHandler};
+use songbird::tracks::TrackHandle;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::warn;
+
+// seeks over sponsor segments of a track as it plays and says so in the channel
+#[derive(Clone)]
+pub struct SegmentSkipper {
+    // filled in once the API answers, the track plays in the meantime
+    segments: Arc<OnceLock<Vec<Segment>>>,
+    // each segment is skipped only once, so seeking back into one plays it
+    skipped: Arc<Mutex<Vec<usize>>>,
+    clock: PlaybackClock,
+    http: Arc<serenity::Http>,
+    channel: serenity::ChannelId,
+}
+
+impl SegmentSkipper {
+    pub fn new(
+        provider: SponsorBlock,
+        video_id: String,
+        clock: PlaybackClock,
+        http: Arc<serenity::Http>,
+        channel: serenity::ChannelId,
+    ) -> Self {
+        let segments = Arc::new(OnceLock::new());
+
+        let fetched = segments.clone();
+        tokio::spawn(async move {
+            // tracks are played whole when the API can't be reached
+            let segments = match provider.segments(&video_id).await {
+                Ok(segments) => segments,
+                Err(err) => {
+                    warn!(error = %err, video_id, "sponsor segments not fetched");
+                    Vec::new()
+                }
+            };
+            let _ = fetched.set(segments);
+        });
+
+        SegmentSkipper {
+            segments,
+            skipped: Default::default(),
+            clock,
+            http,
+            channel,
+        }
+    }
+
+    pub fn register(self, track: &TrackHandle) {
+        let _ = track.add_event(Event::Periodic(TICK, None), self);
+    }
+
+    // segment the track is in that wasn't skipped yet
+    fn current_segment(&self) -> Option<Segment> {
+        let position = self.clock.get()?;
+        let segments = self.segments.get()?;
+
+        let (index, segment) = segments
+            .iter()
+            .enumerate()
+            .find(|(_, segment)| segment.start <= position && position < segment.end)?;
+
+        let mut skipped = self.skipped.lock().unwrap();
+        if skipped.contains(&index) {
+            return None;
+        }
+        skipped.push(index);
+
+        Some(segment.clone())
+    }
+}
+
+#[async_trait]
+impl EventHandler for SegmentSkipper {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(&[(_, handle)]) = ctx else {
+            return None;
+        };
+
+        let segment = self.current_segment()?;
+        // not waited for, it would hold up the events of every track
+        drop(handle.seek(segment.end));
+
+        let secs = (segment.end - segment.start).as_secs();
+        let message = format!("Skipped **{}** segment ({secs}s)", segment.category);
+        if let Err(err) = self.channel.say(&self.http, message).await {
+            warn!(error = %err, "skipped segment not announced");
+        }
+
+        None
+    }
+}
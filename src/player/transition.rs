@@ -0,0 +1,194 @@
+use crate::audio::PlaybackClock;
+use crate::state::GuildState;
+use serenity::async_trait;
+use songbird::{
+    events::{Event, EventContext, EventHandler},
+    tracks::{TrackHandle, TrackQueue},
+};
+use std::f32::consts::FRAC_PI_2;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// how often the position of the track is checked
+pub const TICK: Duration = Duration::from_millis(50);
+pub const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+// the next track is loaded this long before it has to start
+const PRELOAD_AHEAD: Duration = Duration::from_secs(5);
+// clips are checked again this soon at the latest when their end was missed by little
+const MIN_CLIP_DELAY: Duration = Duration::from_millis(20);
+
+// how a guild moves from one queued track to the next
+#[derive(Clone, Copy, Default)]
+pub struct Transitions {
+    // tracks overlap by this long with one fading out and the other in
+    pub crossfade: Duration,
+    // loads and decodes the next track ahead so it starts right when the previous ends
+    pub gapless: bool,
+}
+
+#[derive(Default)]
+struct TransitionState {
+    preloaded: bool,
+    // the next track once it was started early for the crossfade
+    next: Option<TrackHandle>,
+}
+
+// registered on every queued track for periodic and end events
+#[derive(Clone)]
+pub struct TransitionHandler {
+    guild: Arc<GuildState>,
+    queue: TrackQueue,
+    clock: PlaybackClock,
+    state: Arc<Mutex<TransitionState>>,
+}
+
+impl TransitionHandler {
+    pub fn new(guild: Arc<GuildState>, queue: TrackQueue, clock: PlaybackClock) -> Self {
+        TransitionHandler {
+            guild,
+            queue,
+            clock,
+            state: Default::default(),
+        }
+    }
+
+    pub fn register(self, track: &TrackHandle) {
+        let _ = track.add_event(Event::Periodic(TICK, None), self.clone());
+        let _ = track.add_event(Event::Track(songbird::TrackEvent::End), self);
+    }
+
+    // the track queued after this one, while this one is the current
+    fn next_track(&self, current: &TrackHandle) -> Option<TrackHandle> {
+        let queue = self.queue.current_queue();
+
+        match queue.as_slice() {
+            [first, next, ..] if first.uuid() == current.uuid() => Some(next.clone()),
+            _ => None,
+        }
+    }
+
+    // real time left until the end of the track, None if its length isn't known
+    fn remaining(&self) -> Option<Duration> {
+        let (position, duration) = (self.clock.get()?, self.clock.end()?);
+        let speed = self.guild.filters.settings().speed;
+
+        Some(duration.saturating_sub(position).div_f32(speed))
+    }
+
+    fn tick(&self, current: &TrackHandle) {
+        let transitions = *self.guild.transitions.read().unwrap();
+        if transitions.crossfade.is_zero() && !transitions.gapless {
+            return;
+        }
+
+        let Some(remaining) = self.remaining() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+
+        if !state.preloaded && remaining <= transitions.crossfade + PRELOAD_AHEAD {
+            let Some(next) = self.next_track(current) else {
+                return;
+            };
+
+            drop(next.make_playable());
+            state.preloaded = true;
+        }
+
+        if transitions.crossfade.is_zero() || remaining > transitions.crossfade {
+            return;
+        }
+
+        if state.next.is_none() {
+            let Some(next) = self.next_track(current) else {
+                return;
+            };
+
+            let _ = next.set_volume(0.0);
+            let _ = next.play();
+            state.next = Some(next);
+        }
+
+        // equal power fade, from 1 to 0 over the crossfade
+        let fade = remaining.as_secs_f32() / transitions.crossfade.as_secs_f32();
+        let _ = current.set_volume((fade * FRAC_PI_2).sin());
+        if let Some(next) = &state.next {
+            let _ = next.set_volume((fade * FRAC_PI_2).cos());
+        }
+    }
+
+    // the track could have been skipped in the middle of fading
+    fn end(&self) {
+        if let Some(next) = self.state.lock().unwrap().next.take() {
+            let _ = next.set_volume(1.0);
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for TransitionHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(&[(state, handle)]) = ctx else {
+            return None;
+        };
+
+        if state.playing.is_done() {
+            self.end();
+        } else {
+            self.tick(handle);
+        }
+
+        None
+    }
+}
+
+// stops a clip at its end. Songbird counts played time while the end is on the track's own
+// timeline, so the delay is scaled by the speed and checked again once it fires, as changes
+// of speed and seeks move it
+#[derive(Clone)]
+pub struct ClipStopper {
+    guild: Arc<GuildState>,
+    clock: PlaybackClock,
+    end: Duration,
+}
+
+impl ClipStopper {
+    pub fn new(guild: Arc<GuildState>, clock: PlaybackClock, end: Duration) -> Self {
+        ClipStopper { guild, clock, end }
+    }
+
+    pub fn register(self, track: &TrackHandle, start: Option<Duration>) {
+        let delay = self.remaining(start.unwrap_or_default());
+
+        let _ = track.add_event(Event::Delayed(delay), self);
+    }
+
+    // real time until the end from the position on the track's timeline
+    fn remaining(&self, position: Duration) -> Duration {
+        let speed = self.guild.filters.settings().speed;
+
+        self.end.saturating_sub(position).div_f32(speed)
+    }
+}
+
+#[async_trait]
+impl EventHandler for ClipStopper {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(&[(state, handle)]) = ctx else {
+            return None;
+        };
+        if state.playing.is_done() {
+            return None;
+        }
+
+        if self.clock.clip_ended() {
+            // the queue moves on the same way as when skipping
+            let _ = handle.stop();
+            return None;
+        }
+
+        let position = self.clock.get().unwrap_or(state.position);
+
+        Some(Event::Delayed(self.remaining(position).max(MIN_CLIP_DELAY)))
+    }
+}
@@ -0,0 +1,76 @@
+use reqwest::{Client as HttpClient, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+
+// parts of a video that are skipped, other categories like music offtopic are played
+const CATEGORIES: &str = r#"["sponsor","intro","outro"]"#;
+
+#[derive(Clone)]
+pub struct Segment {
+    pub start: Duration,
+    pub end: Duration,
+    pub category: String,
+}
+
+// HTTP API compatible with SponsorBlock, so a local server can be used in its place
+pub struct SponsorBlock {
+    client: HttpClient,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct ProviderSegment {
+    segment: [f64; 2],
+    category: String,
+}
+
+impl SponsorBlock {
+    pub fn new(client: HttpClient, url: String) -> Self {
+        SponsorBlock { client, url }
+    }
+
+    pub async fn segments(&self, video_id: &str) -> Result<Vec<Segment>, reqwest::Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/api/skipSegments",
+                self.url.trim_end_matches('/')
+            ))
+            .query(&[("videoID", video_id), ("categories", CATEGORIES)])
+            .send()
+            .await?;
+
+        // videos without segments are not found
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+
+        let response = response.error_for_status()?.text().await?;
+        let segments = match serde_json::from_str::<Vec<ProviderSegment>>(&response) {
+            Ok(segments) => segments,
+            Err(err) => {
+                warn!(video_id, error = %err, "invalid segments");
+                Vec::new()
+            }
+        };
+
+        // negative, NaN or too large times are left out
+        let mut segments = segments
+            .into_iter()
+            .filter_map(|segment| {
+                let [start, end] = segment.segment.map(Duration::try_from_secs_f64);
+                let (start, end) = (start.ok()?, end.ok()?);
+
+                (end > start).then_some(Segment {
+                    start,
+                    end,
+                    category: segment.category,
+                })
+            })
+            .collect::<Vec<_>>();
+        segments.sort_by_key(|segment| segment.start);
+
+        Ok(segments)
+    }
+}
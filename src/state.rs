@@ -0,0 +1,97 @@
+use crate::audio::{FilterHandle, LoudnessCache};
+use crate::commands::sources::{
+    AudioCache, CircuitBreaker, ResolverCache, SearchSource, SourceCaches,
+};
+use crate::config::Config;
+use crate::db::Database;
+use crate::metrics::Metrics;
+use crate::network::Network;
+use crate::player::{History, PlayerEvents, Presence, Transitions};
+use dashmap::DashMap;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, MessageId};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+// User data, which is stored and accessible in all command invocations
+pub struct Data {
+    pub config: Arc<Config>,
+    pub loudness: LoudnessCache,
+    pub db: Database,
+    // resolved songs and downloaded audio shared by all guilds
+    pub caches: SourceCaches,
+    pub presence: Presence,
+    pub metrics: Metrics,
+    // proxies, cookies and addresses of requests to YouTube
+    pub network: Arc<Network>,
+    pub events: PlayerEvents,
+    // context of the bot once it is ready, for the API which isn't driven by discord events
+    pub discord: OnceLock<serenity::Context>,
+    guilds: DashMap<GuildId, Arc<GuildState>>,
+}
+
+impl Data {
+    pub fn new(config: Config) -> std::io::Result<Self> {
+        let db = Database::open(config.database_path.clone())?;
+
+        let network = Arc::new(Network::new(&config));
+        let caches = SourceCaches {
+            resolver: ResolverCache::new(config.resolver_cache_size, config.resolver_cache_ttl),
+            audio: match &config.audio_cache_dir {
+                Some(dir) => Some(AudioCache::open(dir.clone(), config.audio_cache_size)?),
+                None => None,
+            },
+        };
+
+        Ok(Data {
+            config: Arc::new(config),
+            loudness: LoudnessCache::default(),
+            db,
+            caches,
+            presence: Presence::default(),
+            metrics: Metrics::default(),
+            network,
+            events: PlayerEvents::default(),
+            discord: OnceLock::new(),
+            guilds: DashMap::new(),
+        })
+    }
+
+    // get state of the guild, creating it on first use
+    pub fn guild(&self, guild_id: GuildId) -> Arc<GuildState> {
+        self.guilds
+            .entry(guild_id)
+            .or_insert_with(|| Arc::new(GuildState::new(&self.config)))
+            .clone()
+    }
+}
+
+// player settings of a single guild, shared with its playing tracks
+pub struct GuildState {
+    pub filters: FilterHandle,
+    pub transitions: RwLock<Transitions>,
+    pub history: Mutex<History>,
+    // queues related tracks when the queue runs out
+    pub autoplay: AtomicBool,
+    // set when the queue is stopped so autoplay doesn't start it again, until something
+    // is queued
+    pub stopped: AtomicBool,
+    pub default_source: RwLock<SearchSource>,
+    // message with the player buttons, edited when the track changes
+    pub now_playing: Mutex<Option<(ChannelId, MessageId)>>,
+    pub breaker: CircuitBreaker,
+}
+
+impl GuildState {
+    fn new(config: &Config) -> Self {
+        GuildState {
+            filters: FilterHandle::default(),
+            transitions: RwLock::new(config.transitions),
+            history: Mutex::default(),
+            autoplay: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            default_source: RwLock::new(config.default_source),
+            now_playing: Mutex::default(),
+            breaker: CircuitBreaker::default(),
+        }
+    }
+}
@@ -12,7 +12,9 @@ use symphonia_core::codecs::CodecParameters;
 use symphonia_core::errors::{seek_error, Result, SeekErrorKind};
 use symphonia_core::formats::prelude::*;
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataLog};
+use symphonia_core::meta::{
+    Metadata, MetadataBuilder, MetadataLog, MetadataRevision, StandardTagKey, Tag, Value,
+};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 
 use crate::common::{FrameHeader, MpegLayer};
@@ -99,6 +101,8 @@ impl FormatReader for MpaReader {
         // Use the header to populate the codec parameters.
         let mut params = CodecParameters::new();
 
+        let mut metadata = MetadataLog::default();
+
         params
             .for_codec(header.codec())
             .with_sample_rate(header.sample_rate)
@@ -111,6 +115,12 @@ impl FormatReader for MpaReader {
             let (delay, padding) = if let Some(lame_tag) = info_tag.lame {
                 params.with_delay(lame_tag.enc_delay).with_padding(lame_tag.enc_padding);
 
+                // Expose the ReplayGain values as standard tags so they are found the same way as
+                // those from ID3v2 or APE tags.
+                if let Some(rev) = lame_tag_replaygain_metadata(&lame_tag) {
+                    metadata.push(rev);
+                }
+
                 (lame_tag.enc_delay, lame_tag.enc_padding)
             }
             else {
@@ -161,7 +171,7 @@ impl FormatReader for MpaReader {
             reader: source,
             tracks: vec![Track::new(0, params)],
             cues: Vec::new(),
-            metadata: Default::default(),
+            metadata,
             options: *options,
             first_packet_pos,
             next_packet_ts: 0,
@@ -801,6 +811,34 @@ fn try_read_info_tag_inner(buf: &[u8], header: &FrameHeader) -> Result<Option<Xi
     Ok(Some(XingInfoTag { num_frames, num_bytes, toc, quality, is_cbr, lame }))
 }
 
+/// Build a metadata revision containing the ReplayGain values of a LAME tag, if any are present.
+fn lame_tag_replaygain_metadata(lame_tag: &LameTag) -> Option<MetadataRevision> {
+    // The radio gain is the track gain, and the audiophile gain is the album gain.
+    let tags = [
+        (StandardTagKey::ReplayGainTrackGain, "REPLAYGAIN_TRACK_GAIN", lame_tag.replaygain_radio),
+        (
+            StandardTagKey::ReplayGainAlbumGain,
+            "REPLAYGAIN_ALBUM_GAIN",
+            lame_tag.replaygain_audiophile,
+        ),
+        (StandardTagKey::ReplayGainTrackPeak, "REPLAYGAIN_TRACK_PEAK", lame_tag.replaygain_peak),
+    ];
+
+    if tags.iter().all(|(_, _, value)| value.is_none()) {
+        return None;
+    }
+
+    let mut builder = MetadataBuilder::new();
+
+    for (std_key, key, value) in tags {
+        if let Some(value) = value {
+            builder.add_tag(Tag::new(Some(std_key), key, Value::Float(f64::from(value))));
+        }
+    }
+
+    Some(builder.metadata())
+}
+
 fn parse_lame_tag_replaygain(value: u16, expected_name: u8) -> Option<f32> {
     // The 3 most-significant bits are the name code.
     let name = ((value & 0xe000) >> 13) as u8;
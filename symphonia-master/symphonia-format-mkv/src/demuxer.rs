@@ -10,7 +10,7 @@ use std::convert::TryFrom;
 use std::io::{Seek, SeekFrom};
 
 use symphonia_core::audio::Layout;
-use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_VORBIS};
+use symphonia_core::codecs::{CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS};
 use symphonia_core::errors::{
     decode_error, end_of_stream_error, seek_error, unsupported_error, Error, Result, SeekErrorKind,
 };
@@ -67,6 +67,17 @@ struct ClusterState {
     end: Option<u64>,
 }
 
+/// Get the pre-skip (encoder delay) in frames from an Opus identification header.
+fn opus_pre_skip_from_codec_private(codec_private: &[u8]) -> Option<u32> {
+    const OPUS_MAGIC: &[u8] = b"OpusHead";
+
+    if codec_private.len() < 12 || !codec_private.starts_with(OPUS_MAGIC) {
+        return None;
+    }
+
+    Some(u32::from(u16::from_le_bytes([codec_private[10], codec_private[11]])))
+}
+
 fn vorbis_extra_data_from_codec_private(extra: &[u8]) -> Result<Box<[u8]>> {
     const VORBIS_PACKET_TYPE_IDENTIFICATION: u8 = 1;
     const VORBIS_PACKET_TYPE_SETUP: u8 = 5;
@@ -500,6 +511,14 @@ impl FormatReader for MkvReader {
                 if let Some(codec_type) = codec_type {
                     codec_params.for_codec(codec_type);
                     if let Some(codec_private) = track.codec_private {
+                        // Opus streams start with priming samples that must be discarded.
+                        if codec_type == CODEC_TYPE_OPUS {
+                            if let Some(pre_skip) = opus_pre_skip_from_codec_private(&codec_private)
+                            {
+                                codec_params.with_delay(pre_skip);
+                            }
+                        }
+
                         let extra_data = match codec_type {
                             CODEC_TYPE_VORBIS => {
                                 vorbis_extra_data_from_codec_private(&codec_private)?
@@ -59,7 +59,7 @@ use super::util;
 //       REV    RVRB                                Reverb
 //                      SEEK                        Seek frame
 //                      SIGN                        Signature frame
-//       SLT    SYLT                                Synchronized lyric/text
+//   x   SLT    SYLT             Lyrics             Synchronized lyric/text
 //       STC    SYTC                                Synchronized tempo codes
 //   x   TAL    TALB             Album              Album/Movie/Show title
 //   x   TBP    TBPM             Bpm                BPM (beats per minute)
@@ -278,7 +278,7 @@ lazy_static! {
             // m.insert(b"RVRB", read_null_frame);
             // m.insert(b"SEEK", read_null_frame);
             // m.insert(b"SIGN", read_null_frame);
-            // m.insert(b"SYLT", read_null_frame);
+            m.insert(b"SYLT", (read_sylt_frame, Some(StandardTagKey::Lyrics)));
             // m.insert(b"SYTC", read_null_frame);
             m.insert(b"TALB", (read_text_frame, Some(StandardTagKey::Album)));
             m.insert(b"TBPM", (read_text_frame, Some(StandardTagKey::Bpm)));
@@ -803,6 +803,77 @@ fn read_comm_uslt_frame(
     Ok(FrameResult::Tag(tag))
 }
 
+/// Reads a `SYLT` (synchronized lyrics) frame. The lyrics are converted into the LRC format, where
+/// each line is prefixed with its `[mm:ss.xx]` timestamp.
+fn read_sylt_frame(
+    reader: &mut BufReader<'_>,
+    std_key: Option<StandardTagKey>,
+    id: &str,
+) -> Result<FrameResult> {
+    // Encoding, language, timestamp format, and content type. A malformed frame is skipped
+    // rather than failing the whole tag.
+    if reader.bytes_available() < 6 {
+        return invalid_data(id.as_bytes());
+    }
+
+    // The first byte of the frame is the encoding of the text.
+    let encoding = match Encoding::parse(reader.read_byte()?) {
+        Some(encoding) => encoding,
+        _ => return invalid_data(id.as_bytes()),
+    };
+
+    // The next three bytes are the language, encoded into the key as for `USLT` frames.
+    let lang = reader.read_triple_bytes()?;
+
+    let key = if validate_lang_code(lang) {
+        format!("{}!{}", id, as_ascii_str(&lang))
+    }
+    else {
+        id.to_string()
+    };
+
+    // Timestamps may be in milliseconds or MPEG frames. Since the latter requires knowledge of the
+    // audio stream, only millisecond timestamps are supported.
+    if reader.read_byte()? != 2 {
+        return unsupported_frame(id.as_bytes());
+    }
+
+    // The content type (lyrics, transcription, etc.) is ignored.
+    reader.read_byte()?;
+
+    // Content descriptor, skipped like for `USLT` frames.
+    scan_text(reader, encoding, reader.bytes_available() as usize)?;
+
+    let mut lyrics = String::new();
+
+    // The remainder of the frame is a sequence of null-terminated text and timestamp pairs.
+    while reader.bytes_available() > 0 {
+        let text = scan_text(reader, encoding, reader.bytes_available() as usize)?.into_owned();
+
+        // A truncated frame.
+        if reader.bytes_available() < 4 {
+            return invalid_data(id.as_bytes());
+        }
+        let ms = reader.read_be_u32()?;
+
+        // Lines are often written with a leading newline.
+        let text = text.trim_start_matches(['\r', '\n']);
+
+        lyrics.push_str(&format!(
+            "[{:02}:{:02}.{:02}]{}\n",
+            ms / 60_000,
+            ms / 1000 % 60,
+            ms % 1000 / 10,
+            text
+        ));
+    }
+
+    // Create the tag.
+    let tag = Tag::new(std_key, &key, Value::from(lyrics));
+
+    Ok(FrameResult::Tag(tag))
+}
+
 /// Reads a `PCNT` (total file play count) frame.
 fn read_pcnt_frame(
     reader: &mut BufReader<'_>,